@@ -1,12 +1,29 @@
 /// Type alias for probabilities.
 pub type ProbabilityT = f32;
 
+/// How probabilities of paths merging into the same entry are combined.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CombineOp {
+    /// Sum the probabilities, as in CTC prefix beam search.
+    #[default]
+    Sum,
+    /// Keep the larger probability of each component, giving Viterbi (best path) semantics.
+    ///
+    /// `pr_total` is the larger of the two components, the probability of the best single
+    /// path, rather than their sum.
+    Max,
+}
+
 /// Struct representing a single entry in the beam search algorithm.
-#[derive(Debug, Default)]
-pub struct BeamEntry {
+///
+/// `Meta` is an arbitrary payload attached to the entry, such as a language model
+/// state handle or alignment information. It defaults to `()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamEntry<Meta = ()> {
     pub pr_total: ProbabilityT,
     pub pr_non_blank: ProbabilityT,
     pub pr_blank: ProbabilityT,
+    pub meta: Meta,
 }
 
 impl BeamEntry {
@@ -21,10 +38,32 @@ impl BeamEntry {
     ///
     /// A new `BeamEntry` instance with the provided probabilities.
     pub fn new(pr_non_blank: ProbabilityT, pr_blank: ProbabilityT) -> BeamEntry {
+        BeamEntry::with_meta(pr_non_blank, pr_blank, ())
+    }
+}
+
+impl<Meta> BeamEntry<Meta> {
+    /// Creates a new `BeamEntry` with the given probabilities and metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `pr_non_blank` - Probability of a non-blank token.
+    /// * `pr_blank` - Probability of a blank token.
+    /// * `meta` - Metadata attached to the entry.
+    ///
+    /// # Returns
+    ///
+    /// A new `BeamEntry` instance with the provided probabilities and metadata.
+    pub fn with_meta(
+        pr_non_blank: ProbabilityT,
+        pr_blank: ProbabilityT,
+        meta: Meta,
+    ) -> BeamEntry<Meta> {
         BeamEntry {
             pr_total: pr_non_blank + pr_blank,
             pr_non_blank,
             pr_blank,
+            meta,
         }
     }
 
@@ -39,6 +78,36 @@ impl BeamEntry {
         self.pr_blank += pr_blank;
         self.pr_total += pr_blank + pr_non_blank;
     }
+
+    /// Merges the probabilities of another path into the `BeamEntry` using `op`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pr_non_blank` - Probability of a non-blank token of the merged path.
+    /// * `pr_blank` - Probability of a blank token of the merged path.
+    /// * `op` - Operator combining the current and merged probabilities.
+    pub fn combine_probabilities(
+        &mut self,
+        pr_non_blank: ProbabilityT,
+        pr_blank: ProbabilityT,
+        op: CombineOp,
+    ) {
+        match op {
+            CombineOp::Sum => self.update_probabilities(pr_non_blank, pr_blank),
+            CombineOp::Max => {
+                self.pr_non_blank = self.pr_non_blank.max(pr_non_blank);
+                self.pr_blank = self.pr_blank.max(pr_blank);
+                self.pr_total = self.pr_non_blank.max(self.pr_blank);
+            }
+        }
+    }
+}
+
+// Only implemented for the default metadata so `BeamEntry::default()` needs no annotations
+impl Default for BeamEntry {
+    fn default() -> Self {
+        BeamEntry::new(0.0, 0.0)
+    }
 }
 
 #[cfg(test)]
@@ -71,4 +140,47 @@ mod tests {
         assert_eq!(entry.pr_non_blank, 0.3);
         assert_eq!(entry.pr_blank, 0.4);
     }
+
+    #[test]
+    fn test_combine_probabilities() {
+        let mut sum = BeamEntry::new(0.25, 0.125);
+        sum.combine_probabilities(0.5, 0.0625, CombineOp::Sum);
+        assert_eq!(sum, BeamEntry::new(0.75, 0.1875));
+
+        let mut max = BeamEntry::new(0.25, 0.125);
+        max.combine_probabilities(0.5, 0.0625, CombineOp::Max);
+        assert_eq!(
+            max,
+            BeamEntry {
+                pr_total: 0.5,
+                ..BeamEntry::new(0.5, 0.125)
+            }
+        );
+
+        // Paths with disjoint components keep the better path, not the sum of both
+        let mut disjoint = BeamEntry::new(0.5, 0.0);
+        disjoint.combine_probabilities(0.0, 0.5, CombineOp::Max);
+        assert_eq!(disjoint.pr_non_blank, 0.5);
+        assert_eq!(disjoint.pr_blank, 0.5);
+        assert_eq!(disjoint.pr_total, 0.5);
+    }
+
+    #[test]
+    fn test_beam_entry_eq() {
+        let mut entry = BeamEntry::default();
+        entry.update_probabilities(0.25, 0.5);
+
+        assert_eq!(entry, BeamEntry::new(0.25, 0.5));
+        assert_ne!(entry, BeamEntry::new(0.5, 0.25));
+    }
+
+    #[test]
+    fn test_beam_entry_with_meta() {
+        let mut entry = BeamEntry::with_meta(0.25, 0.5, vec![1]);
+        entry.update_probabilities(0.25, 0.0);
+        entry.meta.push(2);
+
+        assert_eq!(entry.pr_total, 1.0);
+        assert_eq!(entry.meta, vec![1, 2]);
+    }
 }
@@ -1,30 +1,604 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
 
-use crate::beam_entry::{BeamEntry, ProbabilityT};
-use crate::sorting::{top_n_elements, ScoredValue};
+use crate::beam_entry::{BeamEntry, CombineOp, ProbabilityT};
+use crate::key_fn::{IdentityKey, KeyFn};
+use crate::sorting::top_n_elements_by;
+use crate::util::{approx_eq, edit_distance, PROBABILITY_EPSILON};
 
-pub struct BeamState {
-    pub entries: HashMap<String, BeamEntry>,
+/// Probability `kl_divergence` assigns to a labeling missing from one of the states.
+const KL_MISSING_MASS: ProbabilityT = 1e-6;
+
+/// What `BeamState::prune_top_k_with` does with entries tied with the `k`-th score.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BoundaryPolicy {
+    /// Drop every entry tied at the boundary, keeping fewer than `k` entries. If no entry
+    /// after the `k`-th shares its score, there is no tie and exactly `k` entries are kept.
+    DropAll,
+    /// Keep every entry tied at the boundary, keeping more than `k` entries.
+    KeepAll,
+    /// Keep exactly `k` entries, breaking ties by the state's `tie_break`.
+    #[default]
+    Deterministic,
+}
+
+/// How entries with equal `pr_total` are ordered when sorting and pruning.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Ascending labeling order.
+    #[default]
+    Lexicographic,
+    /// Shorter labelings first, then ascending labeling order.
+    Length,
+    /// Stable pseudo-random order derived from hashing each labeling with the seed.
+    Seeded(u64),
+}
+
+impl TieBreak {
+    /// Orders two tied labelings, placing the preferred one first.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            TieBreak::Lexicographic => a.cmp(b),
+            TieBreak::Length => a
+                .chars()
+                .count()
+                .cmp(&b.chars().count())
+                .then_with(|| a.cmp(b)),
+            TieBreak::Seeded(seed) => seeded_hash(a, *seed)
+                .cmp(&seeded_hash(b, *seed))
+                .then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+/// FNV-1a hash of `labeling` mixed with `seed`, stable across platforms and Rust versions.
+fn seeded_hash(labeling: &str, seed: u64) -> u64 {
+    let mut hash = 0xcbf29ce484222325 ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+    for byte in labeling.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// Error returned when setting a pruning threshold that is negative or not finite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidThresholdError(pub ProbabilityT);
+
+impl fmt::Display for InvalidThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid pruning threshold {}: must be finite and non-negative",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidThresholdError {}
+
+/// Collection of beam entries keyed by labeling.
+///
+/// `Meta` is the metadata payload carried by each `BeamEntry` and defaults to `()`.
+pub struct BeamState<Meta = ()> {
+    pub entries: HashMap<String, BeamEntry<Meta>>,
     pub pruning: bool,
     pub pruning_threshold: ProbabilityT,
+    /// Beam width to prune to with `prune_top_k` whenever an update grows the entries past
+    /// twice this width.
+    ///
+    /// This is an approximation: a labeling pruned early loses its mass, and a later update
+    /// for it starts again from zero, so results can differ from pruning once at the end.
+    pub online_pruning: Option<usize>,
+    pub key_fn: Arc<dyn KeyFn + Send + Sync>,
+    pub combine_op: CombineOp,
+    pub tie_break: TieBreak,
+    pub max_prefixes: Option<usize>,
+    /// Weight of each symbol, indexed like `alphabet`, multiplied into `advance` frames while
+    /// the state holds only the empty labeling, biasing which symbol starts a labeling.
+    pub initial_symbol_prior: Option<Vec<ProbabilityT>>,
+    /// Log-space penalty on the blank: `advance` multiplies each frame's blank probability by
+    /// `exp(-blank_penalty)`. Raising it favours longer labelings, lowering it shorter ones.
+    pub blank_penalty: ProbabilityT,
+    /// Temperature `advance` applies to each frame: probabilities are raised to `1 / temperature`
+    /// and renormalized. Below 1 sharpens the frame towards greedy decoding, above 1 flattens it.
+    pub temperature: ProbabilityT,
+    /// Frames whose highest probability is below this are treated by `advance` as pure blank,
+    /// skipping all non-blank extensions.
+    ///
+    /// This saves the expansion of near-uniform frames that carry no confident signal, at the
+    /// cost of dropping a symbol that is only weakly emitted in such frames.
+    pub min_frame_peak: ProbabilityT,
+    /// Symbol indices `advance` may extend labelings by. Other non-blank symbols are ignored.
+    pub allowed_symbols: Option<Vec<usize>>,
+    /// Log-space penalty per symbol index: `advance` multiplies the symbol's probability in
+    /// each frame by `exp(-penalty)`, discouraging it without forbidding it.
+    pub symbol_penalty: HashMap<usize, ProbabilityT>,
+    /// Natural log of the factor `advance` divided the entries by to keep their mass at 1.
+    ///
+    /// The decode probability of an entry is `pr_total * exp(log_scale)`, which underflows
+    /// `ProbabilityT` on long inputs, so add `pr_total.ln()` in log space instead.
+    pub log_scale: ProbabilityT,
 }
 
 impl BeamState {
     pub fn new(pruning: bool, pruning_threshold: ProbabilityT) -> BeamState {
+        BeamState::with_metadata(pruning, pruning_threshold)
+    }
+}
+
+impl<Meta: Default + Clone> BeamState<Meta> {
+    /// Creates a new `BeamState` whose entries carry `Meta` metadata.
+    pub fn with_metadata(pruning: bool, pruning_threshold: ProbabilityT) -> BeamState<Meta> {
         BeamState {
             entries: HashMap::new(),
-            pruning: pruning,
-            pruning_threshold: pruning_threshold,
+            pruning,
+            pruning_threshold,
+            online_pruning: None,
+            key_fn: Arc::new(IdentityKey),
+            combine_op: CombineOp::default(),
+            tie_break: TieBreak::default(),
+            max_prefixes: None,
+            initial_symbol_prior: None,
+            blank_penalty: 0.0,
+            temperature: 1.0,
+            min_frame_peak: 0.0,
+            allowed_symbols: None,
+            symbol_penalty: HashMap::new(),
+            log_scale: 0.0,
+        }
+    }
+
+    /// Returns the threshold used by `prune`.
+    pub fn pruning_threshold(&self) -> ProbabilityT {
+        self.pruning_threshold
+    }
+
+    /// Sets the threshold used by subsequent prunes.
+    ///
+    /// Fails without changing the threshold if `threshold` is negative or not finite.
+    pub fn set_pruning_threshold(
+        &mut self,
+        threshold: ProbabilityT,
+    ) -> Result<(), InvalidThresholdError> {
+        if !threshold.is_finite() || threshold < 0.0 {
+            return Err(InvalidThresholdError(threshold));
+        }
+
+        self.pruning_threshold = threshold;
+        Ok(())
+    }
+
+    /// Resets the state to the start of a decode: only the empty labeling, with `pr_blank = 1.0`.
+    pub fn seed_initial(&mut self) {
+        self.entries.clear();
+        self.log_scale = 0.0;
+        self.entries.insert(
+            self.key_fn.key("").into_owned(),
+            BeamEntry::with_meta(0.0, 1.0, Meta::default()),
+        );
+    }
+
+    /// Replaces the entries with a copy of those in `prev`, continuing its decode.
+    pub fn seed_from(&mut self, prev: &BeamState<Meta>) {
+        self.entries = prev.entries.clone();
+        self.log_scale = prev.log_scale;
+    }
+
+    /// Collapses a frame-level symbol path and adds `prob` to the resulting labeling.
+    ///
+    /// Repeated symbols are merged and blanks removed, as in greedy CTC decoding. The mass
+    /// goes to `pr_blank` if the path ends in a blank and to `pr_non_blank` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a non-blank index in `indices` is out of bounds for `alphabet`.
+    pub fn insert_path(
+        &mut self,
+        indices: &[usize],
+        alphabet: &[char],
+        blank_index: usize,
+        prob: ProbabilityT,
+    ) {
+        let mut labeling = String::new();
+        let mut previous = None;
+
+        for &index in indices {
+            if index != blank_index && previous != Some(index) {
+                labeling.push(alphabet[index]);
+            }
+            previous = Some(index);
+        }
+
+        if previous == Some(blank_index) {
+            self.update(labeling, 0.0, prob);
+        } else {
+            self.update(labeling, prob, 0.0);
         }
     }
 
-    pub fn get_probabilities(&self, labeling: &str) -> Option<&BeamEntry> {
-        self.entries.get(labeling)
+    pub fn get_probabilities(&self, labeling: &str) -> Option<&BeamEntry<Meta>> {
+        self.entries.get(self.key_fn.key(labeling).as_ref())
     }
 
     pub fn update(&mut self, labeling: String, pr_non_blank: ProbabilityT, pr_blank: ProbabilityT) {
-        let entry = self.entries.entry(labeling).or_insert(BeamEntry::default());
-        entry.update_probabilities(pr_non_blank, pr_blank);
+        self.update_with_meta(labeling, pr_non_blank, pr_blank, |_| {});
+    }
+
+    /// Like `update`, additionally applying `update_meta` to the entry's metadata.
+    ///
+    /// New entries start with `Meta::default()`.
+    pub fn update_with_meta(
+        &mut self,
+        labeling: String,
+        pr_non_blank: ProbabilityT,
+        pr_blank: ProbabilityT,
+        update_meta: impl FnOnce(&mut Meta),
+    ) {
+        // Reuse the labeling as the key unless `key_fn` derived a different one
+        let folded = match self.key_fn.key(&labeling) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(key) => Some(key),
+        };
+        let key = folded.unwrap_or(labeling);
+        let entry = self
+            .entries
+            .entry(key)
+            .or_insert_with(|| BeamEntry::with_meta(0.0, 0.0, Meta::default()));
+        entry.combine_probabilities(pr_non_blank, pr_blank, self.combine_op);
+        update_meta(&mut entry.meta);
+
+        self.prune_online();
+    }
+
+    /// Prunes to the `online_pruning` width once the entries exceed twice that width.
+    fn prune_online(&mut self) {
+        // Online pruning caps peak memory at twice the beam width
+        if let Some(beam_width) = self.online_pruning {
+            if self.entries.len() > 2 * beam_width {
+                self.prune_top_k(beam_width);
+            }
+        }
+    }
+
+    /// Expands every entry by one frame of symbol probabilities using the CTC prefix recurrence.
+    ///
+    /// Each labeling is extended by a blank (keeping it unchanged), by a repeat of its last
+    /// symbol (collapsed, also keeping it unchanged) and by every other symbol. Paths ending
+    /// in the same labeling are merged with `combine_op`, and the result is pruned to the
+    /// `beam_width` best entries (and by `pruning_threshold` if `pruning` is set). Extended
+    /// labelings inherit a clone of their parent's metadata, and `online_pruning` applies
+    /// while the expanded entries are merged.
+    ///
+    /// Before pruning, the expanded entries are divided by their total mass and the log of
+    /// that total is added to `log_scale`, so scores stay representable over any number of
+    /// frames and `pruning_threshold` acts on each entry's share of the beam.
+    ///
+    /// Whether a symbol repeats the last one is decided on `key_fn` keys, so with
+    /// `CaseFoldKey` an `A` directly after an `a` collapses like a repeated `a`.
+    ///
+    /// If `max_prefixes` is set, at most that many distinct labelings are created during the
+    /// expansion. Once the cap is reached, the mass of any further labeling is folded into its
+    /// longest prefix already present, or into the empty labeling, which always has a slot
+    /// reserved. This keeps the total mass but attributes it to shorter labelings, so it is an
+    /// approximation that only affects frames with more candidates than the cap. Parents are
+    /// expanded best first by `pr_total` and `tie_break`, so the slots go to the children of
+    /// the best labelings regardless of hash order.
+    ///
+    /// A state without entries, such as a fresh `BeamState::default()`, is expanded as if
+    /// `seed_initial` had been called, so the first frame always has the empty labeling to
+    /// extend.
+    ///
+    /// If `initial_symbol_prior` is set and the state holds only the empty labeling, each
+    /// non-blank symbol's probability is multiplied by its prior, so the prior shapes the
+    /// first symbol of every labeling. The blank's prior is ignored.
+    ///
+    /// Before the expansion the frame is rescaled by `temperature`, and then the blank's
+    /// probability is discounted by `blank_penalty` and each other symbol's by its
+    /// `symbol_penalty`. A frame whose highest raw probability is
+    /// below `min_frame_peak` only extends labelings by the blank. If `allowed_symbols` is set,
+    /// labelings are only extended by the symbols it lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Probability of each symbol in this frame.
+    /// * `alphabet` - Character appended to a labeling for each symbol index.
+    /// * `blank_index` - Index of the blank symbol, whose `alphabet` entry is ignored.
+    /// * `beam_width` - Maximum number of entries kept in the returned state.
+    ///
+    /// # Returns
+    ///
+    /// A new `BeamState` with the same settings holding the expanded entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame`, `alphabet` and a set `initial_symbol_prior` differ in length,
+    /// `blank_index` is out of bounds, `beam_width` is 0, `blank_penalty` is not finite or
+    /// `temperature` is not finite and positive, or `allowed_symbols` or `symbol_penalty` holds
+    /// an index out of bounds for `alphabet`.
+    pub fn advance(
+        &self,
+        frame: &[ProbabilityT],
+        alphabet: &[char],
+        blank_index: usize,
+        beam_width: usize,
+    ) -> BeamState<Meta> {
+        assert_eq!(
+            frame.len(),
+            alphabet.len(),
+            "Each symbol needs exactly one character"
+        );
+        assert!(
+            blank_index < frame.len(),
+            "Blank index {} is out of bounds for {} symbols",
+            blank_index,
+            frame.len()
+        );
+        assert!(beam_width > 0, "Beam width must be at least 1");
+        assert!(
+            self.blank_penalty.is_finite(),
+            "Blank penalty {} is not finite",
+            self.blank_penalty
+        );
+        assert!(
+            self.temperature.is_finite() && self.temperature > 0.0,
+            "Temperature {} must be finite and positive",
+            self.temperature
+        );
+        if let Some(prior) = &self.initial_symbol_prior {
+            assert_eq!(
+                prior.len(),
+                alphabet.len(),
+                "Each symbol needs exactly one prior"
+            );
+        }
+        if let Some(allowed) = &self.allowed_symbols {
+            if let Some(symbol) = allowed.iter().find(|&&symbol| symbol >= alphabet.len()) {
+                panic!(
+                    "Allowed symbol {} is out of bounds for {} symbols",
+                    symbol,
+                    alphabet.len()
+                );
+            }
+        }
+        if let Some(symbol) = self
+            .symbol_penalty
+            .keys()
+            .find(|&&symbol| symbol >= alphabet.len())
+        {
+            panic!(
+                "Penalized symbol {} is out of bounds for {} symbols",
+                symbol,
+                alphabet.len()
+            );
+        }
+
+        let seed;
+        let mut parents: Vec<(&String, &BeamEntry<Meta>)> = if self.entries.is_empty() {
+            seed = (
+                self.key_fn.key("").into_owned(),
+                BeamEntry::with_meta(0.0, 1.0, Meta::default()),
+            );
+            vec![(&seed.0, &seed.1)]
+        } else {
+            self.entries.iter().collect()
+        };
+        parents.sort_by(|a, b| {
+            compare_scored(self.tie_break, (a.0, a.1.pr_total), (b.0, b.1.pr_total))
+        });
+
+        let gated = frame.iter().all(|&pr| pr < self.min_frame_peak);
+        let frame = self.weight_frame(frame, blank_index);
+        let at_start = parents.len() == 1 && *parents[0].0 == self.key_fn.key("");
+        let prior = self.initial_symbol_prior.as_deref().filter(|_| at_start);
+
+        let mut next = self.empty_with_settings();
+
+        for (labeling, entry) in parents {
+            let last = labeling.chars().last();
+
+            // Blank keeps the labeling
+            let pr_blank = entry.pr_total * frame[blank_index];
+            next.merge(labeling, 0.0, pr_blank, &entry.meta);
+
+            if gated {
+                continue;
+            }
+
+            for (symbol, &pr) in frame.iter().enumerate() {
+                let allowed = self
+                    .allowed_symbols
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(&symbol));
+                if symbol == blank_index || !allowed {
+                    continue;
+                }
+
+                let pr = prior.map_or(pr, |prior| pr * prior[symbol]);
+                let character = alphabet[symbol];
+                let extended = format!("{}{}", labeling, character);
+
+                // Labelings are stored by key, so compare the symbol's key with their end
+                let repeat = last.is_some()
+                    && self.key_fn.key(&extended[labeling.len()..]).chars().last() == last;
+
+                if repeat {
+                    // A repeat without a blank in between collapses into the same labeling,
+                    // while extending by the same symbol requires a preceding blank
+                    next.merge(labeling, entry.pr_non_blank * pr, 0.0, &entry.meta);
+                    next.merge(&extended, entry.pr_blank * pr, 0.0, &entry.meta);
+                } else {
+                    next.merge(&extended, entry.pr_total * pr, 0.0, &entry.meta);
+                }
+            }
+        }
+
+        next.log_scale += next.normalize();
+        if next.pruning {
+            next.prune();
+        }
+        next.prune_top_k(beam_width);
+
+        next
+    }
+
+    /// Returns a copy of `frame` with the per-frame options applied.
+    fn weight_frame(&self, frame: &[ProbabilityT], blank_index: usize) -> Vec<ProbabilityT> {
+        let mut weighted = frame.to_vec();
+
+        if self.temperature != 1.0 {
+            for pr in weighted.iter_mut() {
+                *pr = pr.powf(self.temperature.recip());
+            }
+
+            let total: ProbabilityT = weighted.iter().sum();
+            if total > 0.0 {
+                for pr in weighted.iter_mut() {
+                    *pr /= total;
+                }
+            }
+        }
+
+        weighted[blank_index] *= (-self.blank_penalty).exp();
+        for (&symbol, &penalty) in &self.symbol_penalty {
+            weighted[symbol] *= (-penalty).exp();
+        }
+
+        weighted
+    }
+
+    /// Returns a state without entries that shares all settings and the `log_scale` of `self`.
+    fn empty_with_settings(&self) -> BeamState<Meta> {
+        BeamState {
+            entries: HashMap::new(),
+            pruning: self.pruning,
+            pruning_threshold: self.pruning_threshold,
+            online_pruning: self.online_pruning,
+            key_fn: Arc::clone(&self.key_fn),
+            combine_op: self.combine_op,
+            tie_break: self.tie_break,
+            max_prefixes: self.max_prefixes,
+            initial_symbol_prior: self.initial_symbol_prior.clone(),
+            blank_penalty: self.blank_penalty,
+            temperature: self.temperature,
+            min_frame_peak: self.min_frame_peak,
+            allowed_symbols: self.allowed_symbols.clone(),
+            symbol_penalty: self.symbol_penalty.clone(),
+            log_scale: self.log_scale,
+        }
+    }
+
+    /// Divides every entry by the total `pr_total` and returns the log of that total.
+    ///
+    /// A state without mass is left unchanged and 0 is returned.
+    fn normalize(&mut self) -> ProbabilityT {
+        // Sum in sorted order, as float addition in hash order would vary between runs
+        let mut scores: Vec<ProbabilityT> =
+            self.entries.values().map(|entry| entry.pr_total).collect();
+        scores.sort_by(|a, b| a.total_cmp(b));
+        let total: ProbabilityT = scores.iter().sum();
+
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        for entry in self.entries.values_mut() {
+            entry.pr_non_blank /= total;
+            entry.pr_blank /= total;
+            entry.pr_total /= total;
+        }
+
+        total.ln()
+    }
+
+    /// Like `advance`, first raising each of the frame's probabilities to the power `confidence`.
+    ///
+    /// This multiplies the frame's log-probabilities by `confidence`: 1 leaves the frame
+    /// unchanged, values towards 0 flatten it so an unreliable (e.g. low SNR) frame has less
+    /// influence on the ranking. The weighted frame is renormalized to sum to 1.
+    ///
+    /// A `confidence` of 0 ignores the frame entirely, returning the entries unchanged, so a
+    /// frame flagged as noise (e.g. by voice activity detection) can be masked out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is outside `[0, 1]` (including NaN), and under the same
+    /// conditions as `advance`.
+    pub fn advance_weighted(
+        &self,
+        frame: &[ProbabilityT],
+        confidence: ProbabilityT,
+        alphabet: &[char],
+        blank_index: usize,
+        beam_width: usize,
+    ) -> BeamState<Meta> {
+        assert!(
+            (0.0..=1.0).contains(&confidence),
+            "Confidence {} must be between 0 and 1",
+            confidence
+        );
+
+        if confidence == 0.0 {
+            let mut unchanged = self.empty_with_settings();
+            unchanged.entries = self.entries.clone();
+            return unchanged;
+        }
+
+        let mut weighted: Vec<ProbabilityT> = frame.iter().map(|pr| pr.powf(confidence)).collect();
+
+        let total: ProbabilityT = weighted.iter().sum();
+        if total > 0.0 {
+            for pr in weighted.iter_mut() {
+                *pr /= total;
+            }
+        }
+
+        self.advance(&weighted, alphabet, blank_index, beam_width)
+    }
+
+    /// Merges a path into the entry for `labeling`, creating it with a clone of `meta` if it
+    /// is new.
+    ///
+    /// Respects `max_prefixes` by folding into an existing prefix once the cap is reached,
+    /// and `online_pruning` like `update`.
+    fn merge(
+        &mut self,
+        labeling: &str,
+        pr_non_blank: ProbabilityT,
+        pr_blank: ProbabilityT,
+        meta: &Meta,
+    ) {
+        let mut key = self.key_fn.key(labeling);
+
+        if let Some(max_prefixes) = self.max_prefixes {
+            let empty_key = self.key_fn.key("");
+            let reserved =
+                usize::from(key != empty_key && !self.entries.contains_key(empty_key.as_ref()));
+
+            if !self.entries.contains_key(key.as_ref())
+                && self.entries.len() + reserved >= max_prefixes
+            {
+                key = labeling
+                    .char_indices()
+                    .rev()
+                    .map(|(end, _)| self.key_fn.key(&labeling[..end]))
+                    .find(|prefix| self.entries.contains_key(prefix.as_ref()))
+                    .unwrap_or(empty_key);
+            }
+        }
+
+        let entry = self
+            .entries
+            .entry(key.into_owned())
+            .or_insert_with(|| BeamEntry::with_meta(0.0, 0.0, meta.clone()));
+        entry.combine_probabilities(pr_non_blank, pr_blank, self.combine_op);
+
+        self.prune_online();
     }
 
     pub fn sort(&mut self) -> Vec<(String, ProbabilityT)> {
@@ -38,8 +612,8 @@ impl BeamState {
             .map(|(key, entry)| (key.clone(), entry.pr_total))
             .collect();
 
-        // Sort the entries by the second entry (pr_total) in descending order
-        entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        // Sort the entries by the second entry (pr_total) in descending order, ties by `tie_break`
+        entries.sort_by(|a, b| compare_scored(self.tie_break, (&a.0, a.1), (&b.0, b.1)));
 
         entries
     }
@@ -49,28 +623,322 @@ impl BeamState {
             self.prune();
         }
 
-        let mut entries = self
+        let entries = self
             .entries
             .iter()
-            .map(|(key, beam_entry)| {
-                ScoredValue::new((key.clone(), beam_entry), beam_entry.pr_total)
-            })
+            .map(|(key, beam_entry)| (key.clone(), beam_entry.pr_total))
             .collect::<Vec<_>>();
 
-        entries = top_n_elements(entries, n);
+        // Same ordering as `sort`, so `sort()[..n]` equals `sort_top_n(n)`
+        top_n_elements_by(entries, n, |a, b| {
+            compare_scored(self.tie_break, (&a.0, a.1), (&b.0, b.1))
+        })
+    }
 
-        let results: Vec<(String, ProbabilityT)> = entries
-            .iter()
-            .map(|scored_value| (scored_value.value.0.clone(), scored_value.score))
-            .collect::<Vec<_>>();
+    /// Returns up to `n` entries like `sort_top_n`, skipping any whose edit distance to an
+    /// already selected labeling is below `min_edit_distance`.
+    pub fn sort_top_n_distinct(
+        &mut self,
+        n: usize,
+        min_edit_distance: usize,
+    ) -> Vec<(String, ProbabilityT)> {
+        let mut results: Vec<(String, ProbabilityT)> = Vec::with_capacity(n);
+
+        for (labeling, score) in self.sort() {
+            if results.len() == n {
+                break;
+            }
+
+            let distinct = results
+                .iter()
+                .all(|(selected, _)| edit_distance(selected, &labeling) >= min_edit_distance);
+
+            if distinct {
+                results.push((labeling, score));
+            }
+        }
 
         results
     }
 
+    /// Returns the minimum number of top entries whose normalized `pr_total` covers `fraction` of the mass.
+    pub fn coverage(&self, fraction: ProbabilityT) -> usize {
+        let mut scores: Vec<ProbabilityT> =
+            self.entries.values().map(|entry| entry.pr_total).collect();
+        let total: ProbabilityT = scores.iter().sum();
+
+        if scores.is_empty() || total <= 0.0 {
+            return 0;
+        }
+
+        scores.sort_by(|a, b| b.total_cmp(a));
+
+        let mut cumulative = 0.0;
+        for (count, score) in scores.iter().enumerate() {
+            cumulative += score / total;
+            if cumulative >= fraction || approx_eq(cumulative, fraction, PROBABILITY_EPSILON) {
+                return count + 1;
+            }
+        }
+
+        scores.len()
+    }
+
+    /// Returns the difference in `pr_total` between the best and second best entry.
+    ///
+    /// Returns `None` if there are fewer than two entries.
+    pub fn top_margin(&self) -> Option<ProbabilityT> {
+        let mut top1 = None;
+        let mut top2 = None;
+
+        for score in self.entries.values().map(|entry| entry.pr_total) {
+            if top1.is_none_or(|top| score > top) {
+                top2 = top1;
+                top1 = Some(score);
+            } else if top2.is_none_or(|top| score > top) {
+                top2 = Some(score);
+            }
+        }
+
+        Some(top1? - top2?)
+    }
+
+    /// Returns the KL divergence `D(self || other)` between the normalized `pr_total` distributions.
+    ///
+    /// Labelings missing from one of the states are treated as having probability
+    /// `KL_MISSING_MASS` there. Returns 0 if both states are empty and infinity if only
+    /// one of them is, as an empty state has no distribution to compare against.
+    pub fn kl_divergence(&self, other: &BeamState<Meta>) -> ProbabilityT {
+        let p = self.normalized_scores();
+        let q = other.normalized_scores();
+
+        match (p.is_empty(), q.is_empty()) {
+            (true, true) => return 0.0,
+            (true, false) | (false, true) => return ProbabilityT::INFINITY,
+            (false, false) => {}
+        }
+
+        p.keys()
+            .chain(q.keys().filter(|labeling| !p.contains_key(*labeling)))
+            .map(|labeling| {
+                let pr_p = p.get(labeling).copied().unwrap_or(KL_MISSING_MASS);
+                let pr_q = q.get(labeling).copied().unwrap_or(KL_MISSING_MASS);
+                pr_p * (pr_p / pr_q).ln()
+            })
+            .sum()
+    }
+
+    /// Returns the top entry's share of the total mass, `top.pr_total / total`.
+    ///
+    /// This is a whole-utterance confidence that the top labeling is correct rather than any
+    /// of the others. Returns `None` if the state has no entries or no mass.
+    pub fn top_confidence(&self) -> Option<ProbabilityT> {
+        self.normalized_scores()
+            .into_values()
+            .max_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Returns `true` if the top entry's normalized `pr_total` exceeds `dominance`.
+    pub fn is_converged(&self, dominance: ProbabilityT) -> bool {
+        self.top_confidence()
+            .is_some_and(|confidence| confidence > dominance)
+    }
+
+    /// Returns the effective number of beams, `exp(entropy)` of the normalized `pr_total` distribution.
+    ///
+    /// One dominant beam yields about 1, `n` equally likely beams yield `n`. A state without
+    /// entries or mass yields 0.
+    pub fn effective_beams(&self) -> f32 {
+        let scores = self.normalized_scores();
+
+        if scores.is_empty() {
+            return 0.0;
+        }
+
+        let entropy: ProbabilityT = scores
+            .values()
+            .filter(|pr| **pr > 0.0)
+            .map(|pr| -pr * pr.ln())
+            .sum();
+
+        entropy.exp()
+    }
+
+    /// Returns each entry's `pr_total` divided by the total mass, keyed by labeling.
+    fn normalized_scores(&self) -> HashMap<&str, ProbabilityT> {
+        let total: ProbabilityT = self.entries.values().map(|entry| entry.pr_total).sum();
+
+        if total <= 0.0 {
+            return HashMap::new();
+        }
+
+        self.entries
+            .iter()
+            .map(|(labeling, entry)| (labeling.as_str(), entry.pr_total / total))
+            .collect()
+    }
+
+    /// Buckets entries by `pr_total` into `bins` evenly spaced bins between the minimum and maximum score.
+    pub fn score_histogram(&self, bins: usize) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+
+        if bins == 0 || self.entries.is_empty() {
+            return histogram;
+        }
+
+        let scores = self.entries.values().map(|entry| entry.pr_total);
+        let min = scores
+            .clone()
+            .fold(ProbabilityT::INFINITY, ProbabilityT::min);
+        let max = scores
+            .clone()
+            .fold(ProbabilityT::NEG_INFINITY, ProbabilityT::max);
+        let width = (max - min) / bins as ProbabilityT;
+
+        for score in scores {
+            let bin = if width > 0.0 {
+                ((score - min) / width) as usize
+            } else {
+                0
+            };
+            histogram[bin.min(bins - 1)] += 1;
+        }
+
+        histogram
+    }
+
+    /// Writes all entries as CSV with columns `labeling,pr_total,pr_non_blank,pr_blank`,
+    /// sorted by descending `pr_total`.
+    pub fn to_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut entries: Vec<(&String, &BeamEntry<Meta>)> = self.entries.iter().collect();
+        entries.sort_by(|a, b| {
+            compare_scored(self.tie_break, (a.0, a.1.pr_total), (b.0, b.1.pr_total))
+        });
+
+        writeln!(w, "labeling,pr_total,pr_non_blank,pr_blank")?;
+        for (labeling, entry) in entries {
+            writeln!(
+                w,
+                "{},{},{},{}",
+                escape_csv(labeling),
+                entry.pr_total,
+                entry.pr_non_blank,
+                entry.pr_blank
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn prune(&mut self) {
         self.entries
             .retain(|_, beam_entry| beam_entry.pr_total > self.pruning_threshold);
     }
+
+    /// Keeps entries whose `pr_non_blank` exceeds `nb_threshold` or whose `pr_blank` exceeds
+    /// `blank_threshold`, regardless of their total.
+    pub fn prune_by_component(
+        &mut self,
+        nb_threshold: ProbabilityT,
+        blank_threshold: ProbabilityT,
+    ) {
+        self.entries.retain(|_, beam_entry| {
+            beam_entry.pr_non_blank > nb_threshold || beam_entry.pr_blank > blank_threshold
+        });
+    }
+
+    /// Keeps the smallest set of top entries whose normalized `pr_total` reaches `mass`.
+    ///
+    /// The number of survivors adapts to the distribution: a peaked beam keeps few entries,
+    /// a flat one keeps many. Ties are ordered by `tie_break`. A state without any mass has
+    /// no nucleus and is left unchanged.
+    pub fn prune_nucleus(&mut self, mass: ProbabilityT) {
+        let total: ProbabilityT = self.entries.values().map(|entry| entry.pr_total).sum();
+        if total <= 0.0 {
+            return;
+        }
+
+        let keep = self.coverage(mass);
+        self.prune_top_k(keep);
+    }
+
+    /// Drops all entries whose labeling does not satisfy `pred`.
+    pub fn retain_labelings(&mut self, pred: impl Fn(&str) -> bool) {
+        self.entries.retain(|labeling, _| pred(labeling));
+    }
+
+    /// Keeps only the `k` entries with the highest `pr_total`, breaking ties by `tie_break`.
+    pub fn prune_top_k(&mut self, k: usize) {
+        self.prune_top_k_with(k, BoundaryPolicy::Deterministic);
+    }
+
+    /// Keeps the `k` entries with the highest `pr_total`, resolving entries tied with the
+    /// `k`-th score according to `policy`.
+    pub fn prune_top_k_with(&mut self, k: usize, policy: BoundaryPolicy) {
+        if self.entries.len() <= k {
+            return;
+        }
+
+        if k == 0 {
+            self.entries.clear();
+            return;
+        }
+
+        let mut entries: Vec<(String, BeamEntry<Meta>)> = self.entries.drain().collect();
+        entries.sort_by(|a, b| {
+            compare_scored(self.tie_break, (&a.0, a.1.pr_total), (&b.0, b.1.pr_total))
+        });
+
+        let boundary = entries[k - 1].1.pr_total;
+        let tied = entries[k].1.pr_total == boundary;
+        let keep = match policy {
+            BoundaryPolicy::DropAll if tied => entries
+                .iter()
+                .take_while(|(_, entry)| entry.pr_total > boundary)
+                .count(),
+            BoundaryPolicy::KeepAll => entries
+                .iter()
+                .take_while(|(_, entry)| entry.pr_total >= boundary)
+                .count(),
+            BoundaryPolicy::DropAll | BoundaryPolicy::Deterministic => k,
+        };
+
+        entries.truncate(keep);
+        self.entries = entries.into_iter().collect();
+    }
+}
+
+impl<Meta: Default + Clone> Extend<(String, ProbabilityT, ProbabilityT)> for BeamState<Meta> {
+    /// Routes each `(labeling, pr_non_blank, pr_blank)` tuple through `update`.
+    fn extend<I: IntoIterator<Item = (String, ProbabilityT, ProbabilityT)>>(&mut self, iter: I) {
+        for (labeling, pr_non_blank, pr_blank) in iter {
+            self.update(labeling, pr_non_blank, pr_blank);
+        }
+    }
+}
+
+/// Orders `(labeling, score)` pairs by descending score, breaking ties with `tie_break`.
+fn compare_scored(
+    tie_break: TieBreak,
+    a: (&str, ProbabilityT),
+    b: (&str, ProbabilityT),
+) -> Ordering {
+    b.1.total_cmp(&a.1)
+        .then_with(|| tie_break.compare(a.0, b.0))
+}
+
+/// Orders entries by descending `pr_total`, breaking ties by ascending labeling.
+pub(crate) fn compare_ranked(a: &(String, ProbabilityT), b: &(String, ProbabilityT)) -> Ordering {
+    compare_scored(TieBreak::Lexicographic, (&a.0, a.1), (&b.0, b.1))
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
 impl Default for BeamState {
@@ -82,6 +950,7 @@ impl Default for BeamState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::key_fn::CaseFoldKey;
 
     #[test]
     fn test_beam_state_default() {
@@ -100,6 +969,96 @@ mod tests {
         assert_eq!(beam_state.get_probabilities(&key).unwrap().pr_total, 0.2);
     }
 
+    #[test]
+    fn test_beam_state_combine_op() {
+        let mut sum = BeamState::default();
+        let mut max = BeamState {
+            combine_op: CombineOp::Max,
+            ..BeamState::default()
+        };
+
+        for beam_state in [&mut sum, &mut max] {
+            beam_state.update(String::from("a"), 0.25, 0.0);
+            beam_state.update(String::from("a"), 0.5, 0.0);
+        }
+
+        assert_eq!(sum.get_probabilities("a").unwrap().pr_total, 0.75);
+        assert_eq!(max.get_probabilities("a").unwrap().pr_total, 0.5);
+    }
+
+    #[test]
+    fn test_beam_state_extend() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.extend(vec![
+            (String::from("a"), 0.25, 0.0),
+            (String::from("b"), 0.5, 0.25),
+            (String::from("a"), 0.125, 0.5),
+        ]);
+
+        assert_eq!(beam_state.entries.len(), 2);
+        assert_eq!(
+            *beam_state.get_probabilities("a").unwrap(),
+            BeamEntry::new(0.375, 0.5)
+        );
+        assert_eq!(
+            *beam_state.get_probabilities("b").unwrap(),
+            BeamEntry::new(0.5, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_beam_state_metadata() {
+        #[derive(Debug, Default, Clone, PartialEq)]
+        struct Meta {
+            extensions: usize,
+            last_frame: usize,
+        }
+
+        let mut beam_state: BeamState<Meta> = BeamState::with_metadata(true, 1e-5);
+
+        for frame in 0..3 {
+            beam_state.update_with_meta(String::from("a"), 0.1, 0.0, |meta| {
+                meta.extensions += 1;
+                meta.last_frame = frame;
+            });
+        }
+        beam_state.update(String::from("b"), 0.1, 0.0);
+
+        let meta = &beam_state.get_probabilities("a").unwrap().meta;
+        assert_eq!(meta.extensions, 3);
+        assert_eq!(meta.last_frame, 2);
+        assert_eq!(
+            beam_state.get_probabilities("b").unwrap().meta,
+            Meta::default()
+        );
+    }
+
+    #[test]
+    fn test_beam_state_case_fold_key() {
+        let mut beam_state = BeamState {
+            key_fn: Arc::new(CaseFoldKey),
+            ..BeamState::default()
+        };
+
+        beam_state.update(String::from("A"), 0.1, 0.1);
+        beam_state.update(String::from("a"), 0.2, 0.0);
+
+        assert_eq!(beam_state.entries.len(), 1);
+        assert!(approx_eq(
+            beam_state.get_probabilities("A").unwrap().pr_total,
+            0.4,
+            PROBABILITY_EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_beam_state_is_send() {
+        fn assert_send<T: Send>() {}
+
+        assert_send::<BeamState>();
+    }
+
     #[test]
     fn test_beam_state_prune() {
         let mut beam_state = BeamState::new(true, 0.1);
@@ -115,6 +1074,83 @@ mod tests {
         assert_eq!(beam_state.entries.len(), 1);
     }
 
+    #[test]
+    fn test_beam_state_set_pruning_threshold() {
+        let mut beam_state = BeamState::new(true, 0.05);
+
+        beam_state.update(String::from("a"), 0.01, 0.0);
+        beam_state.update(String::from("b"), 0.1, 0.0);
+        beam_state.update(String::from("c"), 0.3, 0.0);
+        beam_state.prune();
+        assert_eq!(beam_state.entries.len(), 2);
+
+        assert_eq!(beam_state.set_pruning_threshold(0.2), Ok(()));
+        assert_eq!(beam_state.pruning_threshold(), 0.2);
+
+        beam_state.update(String::from("d"), 0.25, 0.0);
+        beam_state.prune();
+        assert_eq!(beam_state.entries.len(), 2);
+        assert!(beam_state.get_probabilities("b").is_none());
+
+        assert_eq!(
+            beam_state.set_pruning_threshold(-0.1),
+            Err(InvalidThresholdError(-0.1))
+        );
+        assert!(beam_state.set_pruning_threshold(ProbabilityT::NAN).is_err());
+        assert!(beam_state
+            .set_pruning_threshold(ProbabilityT::INFINITY)
+            .is_err());
+        assert_eq!(beam_state.pruning_threshold(), 0.2);
+    }
+
+    #[test]
+    fn test_beam_state_prune_by_component() {
+        let mut by_total = BeamState::new(true, 0.1);
+        let mut by_component = BeamState::default();
+
+        for beam_state in [&mut by_total, &mut by_component] {
+            beam_state.update(String::from("a"), 0.08, 0.01);
+            beam_state.update(String::from("b"), 0.01, 0.02);
+            beam_state.update(String::from("c"), 0.1, 0.2);
+        }
+
+        by_total.prune();
+        by_component.prune_by_component(0.05, 0.1);
+
+        assert!(by_total.get_probabilities("a").is_none());
+        assert!(by_component.get_probabilities("a").is_some());
+        assert!(by_component.get_probabilities("b").is_none());
+        assert!(by_component.get_probabilities("c").is_some());
+    }
+
+    #[test]
+    fn test_beam_state_prune_nucleus() {
+        let mut peaked = BeamState::default();
+        peaked.update(String::from("a"), 0.9, 0.0);
+        for i in 0..10 {
+            peaked.update(format!("l{}", i), 0.01, 0.0);
+        }
+
+        let mut flat = BeamState::default();
+        for i in 0..10 {
+            flat.update(format!("l{}", i), 0.1, 0.0);
+        }
+
+        peaked.prune_nucleus(0.9);
+        flat.prune_nucleus(0.9);
+
+        assert_eq!(peaked.entries.len(), 1);
+        assert!(peaked.get_probabilities("a").is_some());
+        assert_eq!(flat.entries.len(), 9);
+
+        let mut massless = BeamState::default();
+        massless.update(String::from("a"), 0.0, 0.0);
+        massless.update(String::from("b"), 0.0, 0.0);
+
+        massless.prune_nucleus(0.9);
+        assert_eq!(massless.entries.len(), 2);
+    }
+
     #[test]
     fn test_beam_state_sort() {
         let mut beam_state = BeamState::default();
@@ -154,4 +1190,906 @@ mod tests {
 
         println!("{:?}", entries);
     }
+
+    #[test]
+    fn test_beam_state_sort_ties_match_sort_top_n() {
+        let mut beam_state = BeamState::default();
+
+        for labeling in ["d", "b", "e", "a", "c"] {
+            beam_state.update(String::from(labeling), 0.1, 0.0);
+        }
+        beam_state.update(String::from("f"), 0.2, 0.0);
+
+        let sorted = beam_state.sort();
+
+        for n in 0..=sorted.len() {
+            assert_eq!(sorted[..n], beam_state.sort_top_n(n)[..]);
+        }
+        assert_eq!(sorted[1].0, "a");
+        assert_eq!(sorted[5].0, "e");
+    }
+
+    #[test]
+    fn test_beam_state_sort_top_n_distinct() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.update(String::from("hello world"), 0.4, 0.0);
+        beam_state.update(String::from("hello word"), 0.3, 0.0);
+        beam_state.update(String::from("yellow bird"), 0.2, 0.0);
+
+        let entries = beam_state.sort_top_n_distinct(2, 2);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "hello world");
+        assert_eq!(entries[1].0, "yellow bird");
+
+        assert_eq!(
+            beam_state.sort_top_n_distinct(3, 0),
+            beam_state.sort_top_n(3)
+        );
+    }
+
+    #[test]
+    fn test_beam_state_coverage() {
+        let mut dominant = BeamState::default();
+        dominant.update(String::from("a"), 0.95, 0.0);
+        dominant.update(String::from("b"), 0.03, 0.0);
+        dominant.update(String::from("c"), 0.02, 0.0);
+
+        assert_eq!(dominant.coverage(0.9), 1);
+
+        let mut flat = BeamState::default();
+        for i in 0..10 {
+            flat.update(format!("l{}", i), 0.1, 0.0);
+        }
+
+        assert_eq!(flat.coverage(0.9), 9);
+        assert_eq!(flat.coverage(1.0), 10);
+        assert_eq!(BeamState::default().coverage(0.9), 0);
+
+        // A NaN score must not panic the sort
+        flat.update(String::from("nan"), ProbabilityT::NAN, 0.0);
+        flat.coverage(0.9);
+    }
+
+    #[test]
+    fn test_beam_state_top_margin() {
+        let mut beam_state = BeamState::default();
+
+        assert_eq!(beam_state.top_margin(), None);
+
+        beam_state.update(String::from("a"), 0.25, 0.0);
+        assert_eq!(beam_state.top_margin(), None);
+
+        beam_state.update(String::from("b"), 0.5, 0.25);
+        beam_state.update(String::from("c"), 0.125, 0.0);
+        beam_state.update(String::from("d"), 0.5, 0.0);
+
+        assert_eq!(beam_state.top_margin(), Some(0.75 - 0.5));
+    }
+
+    #[test]
+    fn test_beam_state_kl_divergence() {
+        let mut a = BeamState::default();
+        a.update(String::from("a"), 0.5, 0.0);
+        a.update(String::from("b"), 0.25, 0.0);
+
+        let mut scaled = BeamState::default();
+        scaled.update(String::from("a"), 0.25, 0.0);
+        scaled.update(String::from("b"), 0.125, 0.0);
+
+        let mut disjoint = BeamState::default();
+        disjoint.update(String::from("c"), 0.5, 0.0);
+
+        assert!(approx_eq(a.kl_divergence(&a), 0.0, PROBABILITY_EPSILON));
+        assert!(approx_eq(
+            a.kl_divergence(&scaled),
+            0.0,
+            PROBABILITY_EPSILON
+        ));
+        assert!(a.kl_divergence(&disjoint) > 10.0);
+
+        let empty = BeamState::default();
+        assert_eq!(empty.kl_divergence(&empty), 0.0);
+        assert_eq!(empty.kl_divergence(&a), ProbabilityT::INFINITY);
+        assert_eq!(a.kl_divergence(&empty), ProbabilityT::INFINITY);
+    }
+
+    #[test]
+    fn test_beam_state_is_converged() {
+        let mut dominated = BeamState::default();
+        dominated.update(String::from("a"), 0.9, 0.0);
+        dominated.update(String::from("b"), 0.05, 0.0);
+        dominated.update(String::from("c"), 0.05, 0.0);
+
+        let mut flat = BeamState::default();
+        for i in 0..4 {
+            flat.update(format!("l{}", i), 0.25, 0.0);
+        }
+
+        assert!(dominated.is_converged(0.8));
+        assert!(!flat.is_converged(0.8));
+        assert!(!BeamState::default().is_converged(0.8));
+    }
+
+    #[test]
+    fn test_beam_state_top_confidence() {
+        let alphabet = ['-', 'a', 'b'];
+
+        let decode = |frame: [ProbabilityT; 3]| {
+            let mut beam_state = BeamState::default();
+            beam_state.seed_initial();
+            for _ in 0..3 {
+                beam_state = beam_state.advance(&frame, &alphabet, 0, 10);
+            }
+
+            beam_state.top_confidence().unwrap()
+        };
+
+        assert!(decode([0.02, 0.96, 0.02]) > 0.9);
+        assert!(decode([0.34, 0.33, 0.33]) < 0.5);
+        assert_eq!(BeamState::default().top_confidence(), None);
+    }
+
+    #[test]
+    fn test_beam_state_effective_beams() {
+        let mut dominant = BeamState::default();
+        dominant.update(String::from("a"), 0.999, 0.0);
+        dominant.update(String::from("b"), 0.0005, 0.0);
+        dominant.update(String::from("c"), 0.0005, 0.0);
+
+        assert!(approx_eq(dominant.effective_beams(), 1.0, 0.02));
+
+        let mut flat = BeamState::default();
+        for i in 0..8 {
+            flat.update(format!("l{}", i), 0.125, 0.0);
+        }
+
+        assert!(approx_eq(flat.effective_beams(), 8.0, 1e-4));
+
+        let mut massless = BeamState::default();
+        massless.update(String::from("a"), 0.0, 0.0);
+
+        assert_eq!(BeamState::default().effective_beams(), 0.0);
+        assert_eq!(massless.effective_beams(), 0.0);
+    }
+
+    #[test]
+    fn test_beam_state_score_histogram() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.update(String::from("a"), 0.0, 0.1);
+        beam_state.update(String::from("b"), 0.0, 0.12);
+        beam_state.update(String::from("c"), 0.0, 0.14);
+        beam_state.update(String::from("d"), 0.0, 0.3);
+        beam_state.update(String::from("e"), 0.0, 0.5);
+
+        assert_eq!(beam_state.score_histogram(4), vec![3, 0, 1, 1]);
+        assert_eq!(beam_state.score_histogram(0), Vec::<usize>::new());
+        assert_eq!(BeamState::default().score_histogram(2), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_beam_state_to_csv() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.update(String::from("ab"), 0.25, 0.5);
+        beam_state.update(String::from("a, \"b\""), 0.125, 0.0);
+
+        let mut buffer = Vec::new();
+        beam_state.to_csv(&mut buffer).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "labeling,pr_total,pr_non_blank,pr_blank");
+        assert_eq!(lines[1], "ab,0.75,0.25,0.5");
+        assert_eq!(lines[2], "\"a, \"\"b\"\"\",0.125,0.125,0");
+    }
+
+    #[test]
+    fn test_beam_state_advance() {
+        let alphabet = ['-', 'a', 'b'];
+        let mut beam_state = BeamState::default();
+        beam_state.seed_initial();
+
+        let first = beam_state.advance(&[0.5, 0.3, 0.2], &alphabet, 0, 10);
+
+        assert_eq!(first.entries.len(), 3);
+        assert_eq!(
+            *first.get_probabilities("").unwrap(),
+            BeamEntry::new(0.0, 0.5)
+        );
+        assert_eq!(
+            *first.get_probabilities("a").unwrap(),
+            BeamEntry::new(0.3, 0.0)
+        );
+        assert_eq!(
+            *first.get_probabilities("b").unwrap(),
+            BeamEntry::new(0.2, 0.0)
+        );
+
+        let mut second = first.advance(&[0.4, 0.5, 0.1], &alphabet, 0, 10);
+
+        // "aa" and "bb" need a blank between the repeats, which neither had yet
+        assert_eq!(second.entries.len(), 5);
+        let expected = [
+            ("", 0.0, 0.2),
+            ("a", 0.25 + 0.15, 0.12),
+            ("b", 0.05 + 0.02, 0.08),
+            ("ab", 0.03, 0.0),
+            ("ba", 0.1, 0.0),
+        ];
+        for (labeling, pr_non_blank, pr_blank) in expected {
+            let entry = second.get_probabilities(labeling).unwrap();
+            assert!(approx_eq(
+                entry.pr_non_blank,
+                pr_non_blank,
+                PROBABILITY_EPSILON
+            ));
+            assert!(approx_eq(entry.pr_blank, pr_blank, PROBABILITY_EPSILON));
+        }
+
+        let total: ProbabilityT = second.sort().iter().map(|(_, pr)| pr).sum();
+        assert!(approx_eq(total, 1.0, PROBABILITY_EPSILON));
+    }
+
+    #[test]
+    fn test_beam_state_advance_beam_width() {
+        let mut beam_state = BeamState::default();
+        beam_state.seed_initial();
+
+        let next = beam_state.advance(&[0.5, 0.3, 0.2], &['-', 'a', 'b'], 0, 2);
+
+        assert_eq!(next.entries.len(), 2);
+        assert!(next.get_probabilities("b").is_none());
+    }
+
+    #[test]
+    fn test_beam_state_advance_blank_last() {
+        let mut beam_state = BeamState::default();
+        beam_state.seed_initial();
+
+        let next = beam_state.advance(&[0.3, 0.2, 0.5], &['a', 'b', '-'], 2, 10);
+
+        assert_eq!(
+            *next.get_probabilities("").unwrap(),
+            BeamEntry::new(0.0, 0.5)
+        );
+        assert_eq!(
+            *next.get_probabilities("a").unwrap(),
+            BeamEntry::new(0.3, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_beam_state_advance_long_input() {
+        let alphabet = ['-', 'a', 'b', 'c', 'd'];
+        let mut beam_state = BeamState::default();
+        beam_state.seed_initial();
+
+        // Alternate symbol and blank frames, each with a 0.9 peak, spelling "abcd" repeatedly
+        let mut expected = String::new();
+        for t in 0..600 {
+            let peak = if t % 2 == 0 {
+                let symbol = 1 + (t / 2) % 4;
+                expected.push(alphabet[symbol]);
+                symbol
+            } else {
+                0
+            };
+            let mut frame = vec![0.025; alphabet.len()];
+            frame[peak] = 0.9;
+
+            beam_state = beam_state.advance(&frame, &alphabet, 0, 8);
+            assert!(!beam_state.entries.is_empty());
+        }
+
+        assert_eq!(beam_state.sort_top_n(1)[0].0, expected);
+        assert!(beam_state.log_scale.is_finite() && beam_state.log_scale < 0.0);
+    }
+
+    #[test]
+    fn test_beam_state_advance_unseeded() {
+        let frame = [0.5, 0.3, 0.2];
+        let alphabet = ['-', 'a', 'b'];
+
+        let mut seeded = BeamState::default();
+        seeded.seed_initial();
+
+        let mut from_seeded = seeded.advance(&frame, &alphabet, 0, 10);
+        let mut from_unseeded = BeamState::default().advance(&frame, &alphabet, 0, 10);
+
+        assert_eq!(from_unseeded.entries.len(), 3);
+        assert_eq!(from_unseeded.sort(), from_seeded.sort());
+    }
+
+    #[test]
+    #[should_panic(expected = "Beam width must be at least 1")]
+    fn test_beam_state_advance_zero_beam_width() {
+        BeamState::default().advance(&[0.5, 0.5], &['-', 'a'], 0, 0);
+    }
+
+    #[test]
+    fn test_beam_state_advance_blank_penalty() {
+        let alphabet = ['-', 'a', 'b'];
+        let frames = [
+            [0.6, 0.3, 0.1],
+            [0.5, 0.1, 0.4],
+            [0.7, 0.2, 0.1],
+            [0.4, 0.1, 0.5],
+            [0.6, 0.3, 0.1],
+            [0.5, 0.2, 0.3],
+        ];
+
+        let lengths: Vec<usize> = [-2.0, 0.0, 1.0, 2.0, 4.0]
+            .into_iter()
+            .map(|blank_penalty| {
+                let mut beam_state = BeamState {
+                    blank_penalty,
+                    ..BeamState::default()
+                };
+                beam_state.seed_initial();
+                for frame in &frames {
+                    beam_state = beam_state.advance(frame, &alphabet, 0, 10);
+                }
+
+                beam_state.sort_top_n(1)[0].0.chars().count()
+            })
+            .collect();
+
+        assert!(lengths.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(lengths[0] < lengths[lengths.len() - 1]);
+    }
+
+    #[test]
+    fn test_beam_state_advance_temperature() {
+        let alphabet = ['-', 'a'];
+        // Greedy picks the blank twice, but "a" has more mass summed over its alignments
+        let frames = [[0.6, 0.4], [0.6, 0.4]];
+
+        let decode = |temperature| {
+            let mut beam_state = BeamState {
+                temperature,
+                ..BeamState::default()
+            };
+            beam_state.seed_initial();
+            for frame in &frames {
+                beam_state = beam_state.advance(frame, &alphabet, 0, 10);
+            }
+
+            beam_state.sort_top_n(1)[0].0.clone()
+        };
+
+        let argmax: Vec<usize> = frames
+            .iter()
+            .map(|frame| {
+                (0..frame.len())
+                    .max_by(|a, b| frame[*a].total_cmp(&frame[*b]))
+                    .unwrap()
+            })
+            .collect();
+        let mut greedy = BeamState::default();
+        greedy.insert_path(&argmax, &alphabet, 0, 1.0);
+        let greedy = greedy.sort_top_n(1)[0].0.clone();
+
+        assert_eq!(greedy, "");
+        assert_eq!(decode(1.0), "a");
+        assert_eq!(decode(0.05), greedy);
+    }
+
+    #[test]
+    fn test_beam_state_advance_min_frame_peak() {
+        let alphabet = ['-', 'a', 'b'];
+        let mut beam_state = BeamState {
+            min_frame_peak: 0.5,
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+
+        let flat = beam_state.advance(&[0.34, 0.33, 0.33], &alphabet, 0, 10);
+        assert_eq!(flat.entries.len(), 1);
+        assert!(flat.get_probabilities("").is_some());
+
+        let peaked = flat.advance(&[0.2, 0.7, 0.1], &alphabet, 0, 10);
+        assert!(peaked.get_probabilities("a").is_some());
+    }
+
+    #[test]
+    fn test_beam_state_advance_allowed_symbols() {
+        let alphabet = ['-', 'a', 'b', '1', '2'];
+        let frames = [
+            [0.1, 0.5, 0.2, 0.15, 0.05],
+            [0.3, 0.1, 0.4, 0.05, 0.15],
+            [0.1, 0.6, 0.1, 0.1, 0.1],
+        ];
+
+        let mut beam_state = BeamState {
+            allowed_symbols: Some(vec![3, 4]),
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+        for frame in &frames {
+            beam_state = beam_state.advance(frame, &alphabet, 0, 10);
+        }
+
+        assert!(beam_state
+            .entries
+            .keys()
+            .all(|labeling| labeling.chars().all(|c| c.is_ascii_digit())));
+        assert!(!beam_state.sort_top_n(1)[0].0.is_empty());
+    }
+
+    #[test]
+    fn test_beam_state_advance_symbol_penalty() {
+        let alphabet = ['-', 'a', ' '];
+        let frames = [
+            [0.2, 0.7, 0.1],
+            [0.2, 0.2, 0.6],
+            [0.2, 0.7, 0.1],
+            [0.2, 0.2, 0.6],
+            [0.2, 0.7, 0.1],
+        ];
+
+        let spaces = |symbol_penalty| {
+            let mut beam_state = BeamState {
+                symbol_penalty,
+                ..BeamState::default()
+            };
+            beam_state.seed_initial();
+            for frame in &frames {
+                beam_state = beam_state.advance(frame, &alphabet, 0, 10);
+            }
+
+            beam_state.sort_top_n(1)[0].0.matches(' ').count()
+        };
+
+        let unpenalized = spaces(HashMap::new());
+        let penalized = spaces(HashMap::from([(2, 2.0)]));
+
+        assert!(unpenalized > 0);
+        assert!(penalized < unpenalized);
+    }
+
+    #[test]
+    fn test_beam_state_advance_inherits_meta() {
+        let mut beam_state: BeamState<usize> = BeamState::with_metadata(true, 1e-5);
+        beam_state.seed_initial();
+        beam_state.entries.get_mut("").unwrap().meta = 7;
+
+        let next = beam_state.advance(&[0.5, 0.3, 0.2], &['-', 'a', 'b'], 0, 10);
+
+        for labeling in ["", "a", "b"] {
+            assert_eq!(next.get_probabilities(labeling).unwrap().meta, 7);
+        }
+    }
+
+    #[test]
+    fn test_beam_state_advance_online_pruning() {
+        let alphabet: Vec<char> = "-abcdefghij".chars().collect();
+        let frame = vec![1.0 / alphabet.len() as ProbabilityT; alphabet.len()];
+
+        let mut beam_state = BeamState {
+            online_pruning: Some(2),
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+
+        for _ in 0..3 {
+            beam_state = beam_state.advance(&frame, &alphabet, 0, 100);
+            assert!(beam_state.entries.len() <= 4);
+        }
+    }
+
+    #[test]
+    fn test_beam_state_advance_case_fold_repeat() {
+        let alphabet = ['-', 'a', 'A'];
+        let mut beam_state = BeamState {
+            key_fn: Arc::new(CaseFoldKey),
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+
+        let first = beam_state.advance(&[0.0, 1.0, 0.0], &alphabet, 0, 10);
+        let second = first.advance(&[0.0, 0.0, 1.0], &alphabet, 0, 10);
+
+        // "A" directly after "a" is a repeat under the folded key and collapses
+        assert_eq!(second.entries.len(), 1);
+        assert_eq!(
+            *second.get_probabilities("a").unwrap(),
+            BeamEntry::new(1.0, 0.0)
+        );
+        assert!(second.get_probabilities("aA").is_none());
+    }
+
+    #[test]
+    fn test_beam_state_advance_initial_symbol_prior() {
+        let alphabet = ['-', 'a', 'A'];
+        let frame = [0.2, 0.42, 0.38];
+
+        let mut unbiased = BeamState::default();
+        unbiased.seed_initial();
+        assert_eq!(
+            unbiased.advance(&frame, &alphabet, 0, 10).sort_top_n(1)[0].0,
+            "a"
+        );
+
+        let mut beam_state = BeamState {
+            initial_symbol_prior: Some(vec![1.0, 0.5, 1.5]),
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+
+        let mut first = beam_state.advance(&frame, &alphabet, 0, 10);
+        assert_eq!(first.sort_top_n(1)[0].0, "A");
+
+        // The prior no longer applies once a symbol has been emitted
+        let second = first.advance(&frame, &alphabet, 0, 10);
+        let pr_a = first.get_probabilities("A").unwrap().pr_total;
+        assert!(approx_eq(
+            second.get_probabilities("Aa").unwrap().pr_non_blank,
+            pr_a * 0.42,
+            PROBABILITY_EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_beam_state_advance_weighted() {
+        let alphabet = ['-', 'a', 'b'];
+        // The middle frame is noise claiming a "b" between two clean frames
+        let frames = [[0.1, 0.8, 0.1], [0.1, 0.1, 0.8], [0.8, 0.1, 0.1]];
+
+        let decode = |confidence: [ProbabilityT; 3]| {
+            let mut beam_state = BeamState::default();
+            beam_state.seed_initial();
+
+            for (frame, confidence) in frames.iter().zip(confidence) {
+                beam_state = beam_state.advance_weighted(frame, confidence, &alphabet, 0, 10);
+            }
+
+            beam_state.sort_top_n(1)[0].0.clone()
+        };
+
+        assert_eq!(decode([1.0, 1.0, 1.0]), "ab");
+        assert_eq!(decode([1.0, 0.1, 1.0]), "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "must be between 0 and 1")]
+    fn test_beam_state_advance_weighted_negative_confidence() {
+        BeamState::default().advance_weighted(&[0.5, 0.5], -1.0, &['-', 'a'], 0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be between 0 and 1")]
+    fn test_beam_state_advance_weighted_nan_confidence() {
+        BeamState::default().advance_weighted(&[0.5, 0.5], ProbabilityT::NAN, &['-', 'a'], 0, 10);
+    }
+
+    #[test]
+    fn test_beam_state_advance_weighted_mask() {
+        let alphabet = ['-', 'a', 'b'];
+        let frames = [
+            [0.1, 0.8, 0.1],
+            [0.1, 0.1, 0.8],
+            [0.1, 0.1, 0.8],
+            [0.8, 0.1, 0.1],
+        ];
+
+        let decode = |weights: [ProbabilityT; 4]| {
+            let mut beam_state = BeamState::default();
+            beam_state.seed_initial();
+            for (frame, weight) in frames.iter().zip(weights) {
+                beam_state = beam_state.advance_weighted(frame, weight, &alphabet, 0, 10);
+            }
+
+            beam_state.sort_top_n(1)[0].0.clone()
+        };
+
+        assert_eq!(decode([1.0; 4]), "ab");
+        assert_eq!(decode([1.0, 0.0, 0.0, 1.0]), "a");
+
+        let mut beam_state = BeamState::default();
+        beam_state.seed_initial();
+        let first = beam_state.advance(&frames[0], &alphabet, 0, 10);
+        let mut masked = first.advance_weighted(&frames[1], 0.0, &alphabet, 0, 10);
+        let mut before = BeamState::default();
+        before.seed_from(&first);
+        assert_eq!(masked.sort(), before.sort());
+    }
+
+    #[test]
+    fn test_beam_state_advance_max_prefixes() {
+        let alphabet: Vec<char> = "-abcdefghij".chars().collect();
+        let frame = vec![1.0 / alphabet.len() as ProbabilityT; alphabet.len()];
+
+        let mut beam_state = BeamState {
+            max_prefixes: Some(4),
+            pruning: false,
+            ..BeamState::default()
+        };
+        beam_state.seed_initial();
+
+        for _ in 0..3 {
+            beam_state = beam_state.advance(&frame, &alphabet, 0, 100);
+
+            assert!(beam_state.entries.len() <= 4);
+            assert!(beam_state.get_probabilities("").is_some());
+
+            let total: ProbabilityT = beam_state
+                .entries
+                .values()
+                .map(|entry| entry.pr_total)
+                .sum();
+            assert!(approx_eq(total, 1.0, PROBABILITY_EPSILON));
+        }
+    }
+
+    #[test]
+    fn test_beam_state_advance_max_prefixes_deterministic() {
+        let alphabet: Vec<char> = "-abcdefghij".chars().collect();
+        let frames: Vec<Vec<ProbabilityT>> = (0..4)
+            .map(|t| {
+                (0..alphabet.len())
+                    .map(|symbol| ((symbol * 3 + t) % 7 + 1) as ProbabilityT)
+                    .collect()
+            })
+            .collect();
+
+        let decode = || {
+            let mut beam_state = BeamState {
+                max_prefixes: Some(5),
+                ..BeamState::default()
+            };
+            beam_state.seed_initial();
+
+            for frame in &frames {
+                beam_state = beam_state.advance(frame, &alphabet, 0, 100);
+            }
+
+            beam_state.sort()
+        };
+
+        let expected = decode();
+        for _ in 0..20 {
+            assert_eq!(decode(), expected);
+        }
+    }
+
+    #[test]
+    fn test_beam_state_seed_initial() {
+        let mut beam_state = BeamState::default();
+        beam_state.update(String::from("a"), 0.5, 0.0);
+
+        beam_state.seed_initial();
+
+        assert_eq!(beam_state.entries.len(), 1);
+        assert_eq!(
+            *beam_state.get_probabilities("").unwrap(),
+            BeamEntry::new(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_beam_state_insert_path() {
+        let alphabet = ['-', 'a', 'b'];
+        let frames: [[ProbabilityT; 3]; 4] = [
+            [0.1, 0.8, 0.1],
+            [0.2, 0.7, 0.1],
+            [0.6, 0.2, 0.2],
+            [0.1, 0.2, 0.7],
+        ];
+        let greedy: Vec<usize> = frames
+            .iter()
+            .map(|frame| {
+                (0..frame.len())
+                    .max_by(|a, b| frame[*a].total_cmp(&frame[*b]))
+                    .unwrap()
+            })
+            .collect();
+
+        let mut beam_state = BeamState::default();
+        beam_state.insert_path(&greedy, &alphabet, 0, 0.25);
+        beam_state.insert_path(&[1, 0, 1, 0], &alphabet, 0, 0.125);
+
+        assert_eq!(greedy, vec![1, 1, 0, 2]);
+        assert_eq!(
+            *beam_state.get_probabilities("ab").unwrap(),
+            BeamEntry::new(0.25, 0.0)
+        );
+        assert_eq!(
+            *beam_state.get_probabilities("aa").unwrap(),
+            BeamEntry::new(0.0, 0.125)
+        );
+    }
+
+    #[test]
+    fn test_beam_state_tie_break() {
+        let labelings = ["ccc", "a", "bb", "dddd", "e", "ff", "g", "hhh"];
+
+        let order = |tie_break| {
+            let mut beam_state = BeamState {
+                tie_break,
+                ..BeamState::default()
+            };
+            for labeling in labelings {
+                beam_state.update(String::from(labeling), 0.1, 0.0);
+            }
+
+            let sorted = beam_state.sort();
+            assert_eq!(sorted[..3], beam_state.sort_top_n(3)[..]);
+
+            sorted
+                .into_iter()
+                .map(|(labeling, _)| labeling)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            order(TieBreak::Lexicographic),
+            vec!["a", "bb", "ccc", "dddd", "e", "ff", "g", "hhh"]
+        );
+        assert_eq!(
+            order(TieBreak::Length),
+            vec!["a", "e", "g", "bb", "ff", "ccc", "hhh", "dddd"]
+        );
+        assert_eq!(order(TieBreak::Seeded(7)), order(TieBreak::Seeded(7)));
+        assert_ne!(order(TieBreak::Seeded(7)), order(TieBreak::Seeded(8)));
+
+        let mut by_length = BeamState {
+            tie_break: TieBreak::Length,
+            ..BeamState::default()
+        };
+        by_length.update(String::from("aa"), 0.1, 0.0);
+        by_length.update(String::from("b"), 0.1, 0.0);
+        by_length.prune_top_k(1);
+        assert!(by_length.get_probabilities("b").is_some());
+    }
+
+    #[test]
+    fn test_beam_state_seed_from() {
+        let frames = [
+            ("a", 0.1, 0.2),
+            ("b", 0.3, 0.0),
+            ("a", 0.05, 0.05),
+            ("c", 0.2, 0.05),
+        ];
+        let n = 2;
+
+        let mut full = BeamState::default();
+        for (labeling, pr_non_blank, pr_blank) in frames {
+            full.update(String::from(labeling), pr_non_blank, pr_blank);
+        }
+
+        let mut prev = BeamState::default();
+        for (labeling, pr_non_blank, pr_blank) in &frames[..n] {
+            prev.update(String::from(*labeling), *pr_non_blank, *pr_blank);
+        }
+
+        let mut continued = BeamState::default();
+        continued.seed_from(&prev);
+        for (labeling, pr_non_blank, pr_blank) in &frames[n..] {
+            continued.update(String::from(*labeling), *pr_non_blank, *pr_blank);
+        }
+
+        assert_eq!(continued.sort(), full.sort());
+    }
+
+    #[test]
+    fn test_beam_state_retain_labelings() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.update(String::from("good"), 0.1, 0.0);
+        beam_state.update(String::from("darn it"), 0.3, 0.0);
+        beam_state.update(String::from("darned"), 0.2, 0.0);
+
+        beam_state.retain_labelings(|labeling| !labeling.contains("darn"));
+
+        assert_eq!(beam_state.entries.len(), 1);
+        assert!(beam_state.get_probabilities("good").is_some());
+    }
+
+    #[test]
+    fn test_beam_state_prune_top_k() {
+        let mut beam_state = BeamState::default();
+
+        beam_state.update(String::from("a"), 0.1, 0.0);
+        beam_state.update(String::from("b"), 0.3, 0.0);
+        beam_state.update(String::from("c"), 0.2, 0.0);
+
+        beam_state.prune_top_k(2);
+
+        assert_eq!(beam_state.entries.len(), 2);
+        assert!(beam_state.get_probabilities("a").is_none());
+    }
+
+    #[test]
+    fn test_beam_state_prune_top_k_boundary_policy() {
+        let mut beam_state = BeamState::default();
+        beam_state.update(String::from("a"), 0.5, 0.0);
+        beam_state.update(String::from("d"), 0.25, 0.0);
+        beam_state.update(String::from("b"), 0.25, 0.0);
+        beam_state.update(String::from("c"), 0.25, 0.0);
+        beam_state.update(String::from("e"), 0.125, 0.0);
+
+        let labelings = |policy| {
+            let mut pruned = BeamState::default();
+            pruned.seed_from(&beam_state);
+            pruned.prune_top_k_with(2, policy);
+
+            let mut labelings: Vec<String> = pruned.entries.into_keys().collect();
+            labelings.sort();
+            labelings
+        };
+
+        assert_eq!(labelings(BoundaryPolicy::DropAll), vec!["a"]);
+        assert_eq!(labelings(BoundaryPolicy::KeepAll), vec!["a", "b", "c", "d"]);
+        assert_eq!(labelings(BoundaryPolicy::Deterministic), vec!["a", "b"]);
+
+        // Without a tie at the boundary every policy keeps exactly `k` entries
+        let mut untied = BeamState::default();
+        untied.update(String::from("a"), 0.5, 0.0);
+        untied.update(String::from("b"), 0.25, 0.0);
+        untied.update(String::from("c"), 0.1, 0.0);
+
+        for policy in [
+            BoundaryPolicy::DropAll,
+            BoundaryPolicy::KeepAll,
+            BoundaryPolicy::Deterministic,
+        ] {
+            let mut pruned = BeamState::default();
+            pruned.seed_from(&untied);
+            pruned.prune_top_k_with(2, policy);
+
+            let mut labelings: Vec<String> = pruned.entries.into_keys().collect();
+            labelings.sort();
+            assert_eq!(labelings, vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn test_beam_state_online_pruning() {
+        let beam_width = 3;
+        let mut online = BeamState {
+            online_pruning: Some(beam_width),
+            ..BeamState::default()
+        };
+        let mut batch = BeamState::default();
+
+        for i in 0..50 {
+            let labeling = format!("l{}", i);
+            let pr = ((i * 7) % 50) as ProbabilityT / 100.0 + 0.01;
+
+            online.update(labeling.clone(), pr, 0.0);
+            batch.update(labeling, pr, 0.0);
+
+            assert!(online.entries.len() <= 2 * beam_width);
+        }
+
+        assert_eq!(batch.entries.len(), 50);
+        assert_eq!(online.sort_top_n(beam_width), batch.sort_top_n(beam_width));
+    }
+
+    #[test]
+    fn test_beam_state_online_pruning_reinsertion() {
+        let mut online = BeamState {
+            online_pruning: Some(1),
+            ..BeamState::default()
+        };
+        let mut batch = BeamState::default();
+
+        for (labeling, pr) in [("x", 0.3), ("y", 0.2), ("z", 0.25), ("y", 0.2)] {
+            online.update(String::from(labeling), pr, 0.0);
+            batch.update(String::from(labeling), pr, 0.0);
+        }
+
+        // "y" was pruned when "z" arrived, so only its second update survives online
+        assert_eq!(online.sort_top_n(1)[0].0, "x");
+        assert_eq!(batch.sort_top_n(1)[0].0, "y");
+        assert!(approx_eq(
+            online.get_probabilities("y").unwrap().pr_total,
+            0.2,
+            PROBABILITY_EPSILON
+        ));
+    }
 }
@@ -0,0 +1,123 @@
+use crate::beam_entry::ProbabilityT;
+
+/// Smallest and largest temperatures considered by `fit_temperature`.
+///
+/// `calibrate_confidence` also raises smaller temperatures to `MIN_TEMPERATURE`.
+const MIN_TEMPERATURE: ProbabilityT = 1e-2;
+const MAX_TEMPERATURE: ProbabilityT = 1e2;
+
+/// Keeps probabilities away from 0 and 1 so their logit and logarithm stay finite.
+const CLAMP_EPSILON: ProbabilityT = 1e-6;
+
+/// Maps a raw confidence score in `[0, 1]` to a calibrated probability.
+///
+/// The score is converted to a logit, divided by `temperature` and mapped back through a
+/// sigmoid. A temperature below 1 pushes scores towards 0 or 1, above 1 towards 0.5.
+/// Temperatures below `MIN_TEMPERATURE`, including 0 and NaN, are raised to it.
+pub fn calibrate_confidence(score: ProbabilityT, temperature: ProbabilityT) -> ProbabilityT {
+    let temperature = temperature.max(MIN_TEMPERATURE);
+    let score = score.clamp(CLAMP_EPSILON, 1.0 - CLAMP_EPSILON);
+    let logit = (score / (1.0 - score)).ln();
+
+    1.0 / (1.0 + (-logit / temperature).exp())
+}
+
+/// Fits the temperature for `calibrate_confidence` on a validation set.
+///
+/// `correct[i]` states whether the hypothesis with raw score `scores[i]` was correct.
+/// The temperature minimizing the log-loss is found by a golden-section search over
+/// `log(temperature)` between `MIN_TEMPERATURE` and `MAX_TEMPERATURE`.
+///
+/// # Panics
+///
+/// Panics if `scores` and `correct` differ in length.
+pub fn fit_temperature(scores: &[ProbabilityT], correct: &[bool]) -> ProbabilityT {
+    assert_eq!(
+        scores.len(),
+        correct.len(),
+        "Each score needs exactly one label"
+    );
+
+    if scores.is_empty() {
+        return 1.0;
+    }
+
+    let loss = |log_temperature: ProbabilityT| log_loss(scores, correct, log_temperature.exp());
+
+    let ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+    let mut low = MIN_TEMPERATURE.ln();
+    let mut high = MAX_TEMPERATURE.ln();
+
+    for _ in 0..64 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+
+        if loss(a) < loss(b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    ((low + high) / 2.0).exp()
+}
+
+/// Mean negative log-likelihood of `correct` under the calibrated `scores`.
+fn log_loss(scores: &[ProbabilityT], correct: &[bool], temperature: ProbabilityT) -> ProbabilityT {
+    let total: ProbabilityT = scores
+        .iter()
+        .zip(correct)
+        .map(|(&score, &correct)| {
+            let pr =
+                calibrate_confidence(score, temperature).clamp(CLAMP_EPSILON, 1.0 - CLAMP_EPSILON);
+            if correct {
+                -pr.ln()
+            } else {
+                -(1.0 - pr).ln()
+            }
+        })
+        .sum();
+
+    total / scores.len() as ProbabilityT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::approx_eq;
+
+    #[test]
+    fn test_calibrate_confidence() {
+        assert!(approx_eq(calibrate_confidence(0.8, 1.0), 0.8, 1e-5));
+        assert!(approx_eq(calibrate_confidence(0.5, 0.1), 0.5, 1e-5));
+        assert!(calibrate_confidence(0.8, 0.5) > 0.8);
+        assert!(calibrate_confidence(0.8, 2.0) < 0.8);
+
+        let sharpest = calibrate_confidence(0.8, MIN_TEMPERATURE);
+        assert_eq!(calibrate_confidence(0.8, 0.0), sharpest);
+        assert_eq!(calibrate_confidence(0.8, -1.0), sharpest);
+        assert_eq!(calibrate_confidence(0.8, ProbabilityT::NAN), sharpest);
+        assert!(sharpest.is_finite());
+    }
+
+    #[test]
+    fn test_fit_temperature_separable() {
+        let scores = [0.7, 0.8, 0.65, 0.3, 0.4, 0.35];
+        let correct = [true, true, true, false, false, false];
+
+        let temperature = fit_temperature(&scores, &correct);
+
+        assert!(temperature > 0.0 && temperature < 1.0);
+        assert!(calibrate_confidence(0.7, temperature) > 0.7);
+        assert!(calibrate_confidence(0.3, temperature) < 0.3);
+    }
+
+    #[test]
+    fn test_fit_temperature_overconfident() {
+        // Half of the confident hypotheses are wrong, so the fit should soften the scores.
+        let scores = [0.9, 0.9, 0.1, 0.1];
+        let correct = [true, false, false, true];
+
+        assert!(fit_temperature(&scores, &correct) > 1.0);
+    }
+}
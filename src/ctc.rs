@@ -0,0 +1,323 @@
+use std::fmt;
+
+use crate::beam_entry::ProbabilityT;
+
+/// Error returned when a symbol index does not fit a row of the probability matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidSymbolError(pub usize);
+
+impl fmt::Display for InvalidSymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid symbol index {}: out of range for the probability matrix",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidSymbolError {}
+
+/// Computes the total CTC probability of `target_indices` under the probability matrix `probs`.
+///
+/// This is `ctc_log_probability` mapped back to linear space, so it underflows to 0 for long
+/// inputs. Prefer `ctc_log_probability` there.
+pub fn ctc_probability(
+    probs: &[Vec<ProbabilityT>],
+    target_indices: &[usize],
+    blank_index: usize,
+) -> Result<ProbabilityT, InvalidSymbolError> {
+    ctc_log_probability(probs, target_indices, blank_index).map(ProbabilityT::exp)
+}
+
+/// Computes the natural log of the total CTC probability of `target_indices` under `probs`.
+///
+/// Implements the CTC forward algorithm, summing over all alignments that collapse to the
+/// target. Unlike beam search this is exact. The forward variables are rescaled to sum to 1
+/// after every frame and the scales accumulated in log space, so long inputs do not underflow.
+///
+/// # Arguments
+///
+/// * `probs` - Probability matrix with one row of symbol probabilities per frame.
+/// * `target_indices` - Symbol indices of the target labeling, without blanks.
+/// * `blank_index` - Index of the blank symbol in each row.
+///
+/// # Returns
+///
+/// The log marginal probability of the target labeling, `NEG_INFINITY` if it is impossible,
+/// or an error if `blank_index` or a target index is out of range for a row.
+pub fn ctc_log_probability(
+    probs: &[Vec<ProbabilityT>],
+    target_indices: &[usize],
+    blank_index: usize,
+) -> Result<ProbabilityT, InvalidSymbolError> {
+    // Target interleaved with blanks: blank, t1, blank, t2, ..., blank
+    let extended: Vec<usize> = std::iter::once(blank_index)
+        .chain(
+            target_indices
+                .iter()
+                .flat_map(|&symbol| [symbol, blank_index]),
+        )
+        .collect();
+
+    if let Some(width) = probs.iter().map(Vec::len).min() {
+        if let Some(&symbol) = extended.iter().find(|&&symbol| symbol >= width) {
+            return Err(InvalidSymbolError(symbol));
+        }
+    }
+
+    let Some(first_frame) = probs.first() else {
+        return Ok(if target_indices.is_empty() {
+            0.0
+        } else {
+            ProbabilityT::NEG_INFINITY
+        });
+    };
+
+    let mut alpha = vec![0.0; extended.len()];
+    alpha[0] = first_frame[blank_index];
+    if extended.len() > 1 {
+        alpha[1] = first_frame[extended[1]];
+    }
+    let mut log_scale = 0.0;
+
+    for frame in &probs[1..] {
+        log_scale += rescale(&mut alpha);
+        let previous = alpha.clone();
+
+        for (s, &symbol) in extended.iter().enumerate() {
+            let mut pr = previous[s];
+            if s >= 1 {
+                pr += previous[s - 1];
+            }
+            // Skipping the blank in between is only allowed between distinct symbols
+            if s >= 2 && symbol != blank_index && symbol != extended[s - 2] {
+                pr += previous[s - 2];
+            }
+
+            alpha[s] = pr * frame[symbol];
+        }
+    }
+
+    let last = extended.len() - 1;
+    let pr = if last >= 1 {
+        alpha[last] + alpha[last - 1]
+    } else {
+        alpha[last]
+    };
+
+    Ok(pr.ln() + log_scale)
+}
+
+/// Divides `alpha` by its sum and returns the log of that sum, leaving an all-zero `alpha` as is.
+fn rescale(alpha: &mut [ProbabilityT]) -> ProbabilityT {
+    let total: ProbabilityT = alpha.iter().sum();
+
+    if total <= 0.0 {
+        return 0.0;
+    }
+
+    for pr in alpha.iter_mut() {
+        *pr /= total;
+    }
+
+    total.ln()
+}
+
+/// Moves the blank column of every row in `probs` from index `from` to index `to`.
+///
+/// The other columns keep their relative order, shifting by one to make room.
+///
+/// # Panics
+///
+/// Panics if `from` or `to` is out of bounds for a row.
+pub fn relocate_blank(probs: &mut [Vec<ProbabilityT>], from: usize, to: usize) {
+    for row in probs.iter_mut() {
+        let blank = row.remove(from);
+        row.insert(to, blank);
+    }
+}
+
+/// Summary of the changes made by `repair_matrix`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Rows that were too short and were padded.
+    pub padded_rows: usize,
+    /// Rows that were too long and were truncated.
+    pub truncated_rows: usize,
+}
+
+impl RepairReport {
+    /// Returns the total number of rows that were changed.
+    pub fn rows_changed(&self) -> usize {
+        self.padded_rows + self.truncated_rows
+    }
+}
+
+/// Makes a ragged probability matrix rectangular with rows of length `width`.
+///
+/// Short rows are padded with `fill` and long rows are truncated.
+pub fn repair_matrix(
+    probs: &mut [Vec<ProbabilityT>],
+    width: usize,
+    fill: ProbabilityT,
+) -> RepairReport {
+    let mut report = RepairReport::default();
+
+    for row in probs.iter_mut() {
+        if row.len() < width {
+            report.padded_rows += 1;
+        } else if row.len() > width {
+            report.truncated_rows += 1;
+        }
+
+        row.resize(width, fill);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{approx_eq, PROBABILITY_EPSILON};
+
+    #[test]
+    fn test_ctc_probability_single_symbol() {
+        let probs = vec![vec![0.4, 0.6], vec![0.3, 0.7]];
+
+        // Alignments "aa", "a-" and "-a"
+        let expected = 0.6 * 0.7 + 0.6 * 0.3 + 0.4 * 0.7;
+
+        assert!(approx_eq(
+            ctc_probability(&probs, &[1], 0).unwrap(),
+            expected,
+            PROBABILITY_EPSILON
+        ));
+        assert!(approx_eq(
+            ctc_probability(&probs, &[], 0).unwrap(),
+            0.4 * 0.3,
+            PROBABILITY_EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_ctc_probability_repeated_symbol_needs_blank() {
+        let probs = vec![vec![0.4, 0.6], vec![0.3, 0.7]];
+        assert_eq!(ctc_probability(&probs, &[1, 1], 0).unwrap(), 0.0);
+        assert_eq!(
+            ctc_log_probability(&probs, &[1, 1], 0),
+            Ok(ProbabilityT::NEG_INFINITY)
+        );
+
+        let probs = vec![vec![0.4, 0.6], vec![0.3, 0.7], vec![0.2, 0.8]];
+        let expected = 0.6 * 0.3 * 0.8;
+        assert!(approx_eq(
+            ctc_probability(&probs, &[1, 1], 0).unwrap(),
+            expected,
+            PROBABILITY_EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_ctc_probability_sums_to_one() {
+        let probs = vec![vec![0.5, 0.2, 0.3], vec![0.1, 0.6, 0.3]];
+
+        let total: ProbabilityT = [vec![], vec![1], vec![2], vec![1, 2], vec![2, 1]]
+            .iter()
+            .map(|target| ctc_probability(&probs, target, 0).unwrap())
+            .sum();
+
+        assert!(approx_eq(total, 1.0, PROBABILITY_EPSILON));
+    }
+
+    #[test]
+    fn test_ctc_probability_blank_last() {
+        let probs = vec![vec![0.6, 0.4], vec![0.7, 0.3]];
+        let expected = 0.6 * 0.7 + 0.6 * 0.3 + 0.4 * 0.7;
+
+        assert!(approx_eq(
+            ctc_probability(&probs, &[0], 1).unwrap(),
+            expected,
+            PROBABILITY_EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_ctc_log_probability_long_input() {
+        let probs = vec![vec![0.5, 0.5]; 200];
+        let expected = 200.0 * (0.5 as ProbabilityT).ln();
+
+        assert!(approx_eq(
+            ctc_log_probability(&probs, &[], 0).unwrap(),
+            expected,
+            1e-3
+        ));
+
+        // 40 * 41 / 2 alignments of a single symbol, each with probability (1 / 20)^40
+        let probs = vec![vec![1.0 / 20.0; 20]; 40];
+        let expected = (820.0 as ProbabilityT).ln() - 40.0 * (20.0 as ProbabilityT).ln();
+
+        assert!(approx_eq(
+            ctc_log_probability(&probs, &[3], 0).unwrap(),
+            expected,
+            1e-3
+        ));
+    }
+
+    #[test]
+    fn test_ctc_probability_invalid_symbol() {
+        let probs = vec![vec![0.4, 0.6], vec![0.3, 0.7]];
+
+        assert_eq!(ctc_probability(&probs, &[2], 0), Err(InvalidSymbolError(2)));
+        assert_eq!(
+            ctc_log_probability(&probs, &[1], 5),
+            Err(InvalidSymbolError(5))
+        );
+    }
+
+    #[test]
+    fn test_relocate_blank() {
+        let mut probs = vec![vec![0.5, 0.2, 0.3], vec![0.1, 0.6, 0.3]];
+        let before = ctc_probability(&probs, &[1, 2], 0).unwrap();
+
+        relocate_blank(&mut probs, 0, 2);
+
+        assert_eq!(probs, vec![vec![0.2, 0.3, 0.5], vec![0.6, 0.3, 0.1]]);
+        assert_eq!(ctc_probability(&probs, &[0, 1], 2).unwrap(), before);
+
+        relocate_blank(&mut probs, 2, 0);
+
+        assert_eq!(probs, vec![vec![0.5, 0.2, 0.3], vec![0.1, 0.6, 0.3]]);
+    }
+
+    #[test]
+    fn test_repair_matrix() {
+        let mut probs = vec![
+            vec![0.5, 0.5],
+            vec![1.0],
+            vec![0.2, 0.3, 0.5],
+            vec![0.1, 0.9],
+        ];
+
+        let report = repair_matrix(&mut probs, 2, 0.0);
+
+        assert_eq!(
+            probs,
+            vec![
+                vec![0.5, 0.5],
+                vec![1.0, 0.0],
+                vec![0.2, 0.3],
+                vec![0.1, 0.9]
+            ]
+        );
+        assert_eq!(
+            report,
+            RepairReport {
+                padded_rows: 1,
+                truncated_rows: 1
+            }
+        );
+        assert_eq!(report.rows_changed(), 2);
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::beam_entry::ProbabilityT;
+use crate::beam_state::compare_ranked;
+
+/// Merges n-best lists from several models into a single ranking.
+///
+/// The score of each labeling is the weighted sum of its scores across `lists`,
+/// where a labeling missing from a list contributes nothing for that list.
+/// The result is sorted like `BeamState::sort`.
+///
+/// # Panics
+///
+/// Panics if `lists` and `weights` differ in length.
+pub fn ensemble_merge(
+    lists: &[Vec<(String, ProbabilityT)>],
+    weights: &[ProbabilityT],
+) -> Vec<(String, ProbabilityT)> {
+    assert_eq!(
+        lists.len(),
+        weights.len(),
+        "Each list needs exactly one weight"
+    );
+
+    let mut scores: HashMap<&str, ProbabilityT> = HashMap::new();
+
+    for (list, weight) in lists.iter().zip(weights) {
+        for (labeling, score) in list {
+            *scores.entry(labeling.as_str()).or_default() += weight * score;
+        }
+    }
+
+    let mut merged: Vec<(String, ProbabilityT)> = scores
+        .into_iter()
+        .map(|(labeling, score)| (labeling.to_string(), score))
+        .collect();
+
+    merged.sort_by(compare_ranked);
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensemble_merge_shared_hypothesis_wins() {
+        let a = vec![(String::from("cat"), 0.5), (String::from("cut"), 0.375)];
+        let b = vec![(String::from("cap"), 0.5), (String::from("cut"), 0.375)];
+
+        let merged = ensemble_merge(&[a, b], &[1.0, 1.0]);
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0], (String::from("cut"), 0.75));
+        assert_eq!(merged[1], (String::from("cap"), 0.5));
+        assert_eq!(merged[2], (String::from("cat"), 0.5));
+    }
+
+    #[test]
+    fn test_ensemble_merge_weights() {
+        let a = vec![(String::from("x"), 1.0)];
+        let b = vec![(String::from("y"), 1.0)];
+
+        let merged = ensemble_merge(&[a, b], &[0.25, 0.75]);
+
+        assert_eq!(merged[0], (String::from("y"), 0.75));
+        assert_eq!(merged[1], (String::from("x"), 0.25));
+    }
+}
@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+
+/// Trait deriving the merge key of a labeling.
+///
+/// Labelings mapping to the same key are merged into a single beam entry.
+pub trait KeyFn {
+    /// Returns the key under which `labeling` is stored.
+    ///
+    /// Implementations should borrow `labeling` when it already is its own key, so that
+    /// callers only allocate when the key differs.
+    fn key<'a>(&self, labeling: &'a str) -> Cow<'a, str>;
+}
+
+/// Uses the raw labeling as its key.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdentityKey;
+
+impl KeyFn for IdentityKey {
+    fn key<'a>(&self, labeling: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(labeling)
+    }
+}
+
+/// Uses the lowercased labeling as its key, merging labelings that differ only in case.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CaseFoldKey;
+
+impl KeyFn for CaseFoldKey {
+    fn key<'a>(&self, labeling: &'a str) -> Cow<'a, str> {
+        if labeling.chars().all(|c| c.to_lowercase().eq([c])) {
+            Cow::Borrowed(labeling)
+        } else {
+            Cow::Owned(labeling.to_lowercase())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_key() {
+        assert_eq!(IdentityKey.key("Abc"), "Abc");
+        assert!(matches!(IdentityKey.key("Abc"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_case_fold_key() {
+        assert_eq!(CaseFoldKey.key("Abc"), "abc");
+        assert!(matches!(CaseFoldKey.key("abc"), Cow::Borrowed(_)));
+    }
+}
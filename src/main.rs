@@ -1,6 +1,13 @@
 mod beam_entry;
 mod beam_state;
+mod calibration;
+mod ctc;
+mod ensemble;
+mod key_fn;
+mod rerank;
+mod result_diff;
 mod sorting;
+mod util;
 
 use beam_entry::BeamEntry;
 // use beam_state::BeamState;
@@ -0,0 +1,53 @@
+use crate::beam_entry::ProbabilityT;
+use crate::util::edit_distance;
+
+/// Selects the hypothesis with the lowest expected edit distance to the others (minimum Bayes risk).
+///
+/// Each candidate in `results` is weighted by its score normalized over the list.
+/// Ties are resolved in favour of the earlier candidate, so a list ranked by
+/// `BeamState::sort` prefers the higher-scoring hypothesis.
+///
+/// Returns `None` if `results` is empty.
+pub fn mbr_rerank(results: &[(String, ProbabilityT)]) -> Option<(String, ProbabilityT)> {
+    let total: ProbabilityT = results.iter().map(|(_, score)| score).sum();
+
+    let mut best: Option<(&(String, ProbabilityT), ProbabilityT)> = None;
+
+    for candidate in results {
+        let risk: ProbabilityT = results
+            .iter()
+            .map(|(other, score)| {
+                let weight = if total > 0.0 { score / total } else { 0.0 };
+                weight * edit_distance(&candidate.0, other) as ProbabilityT
+            })
+            .sum();
+
+        if best.is_none_or(|(_, best_risk)| risk < best_risk) {
+            best = Some((candidate, risk));
+        }
+    }
+
+    best.map(|(candidate, _)| candidate.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbr_rerank_picks_consensus() {
+        let results = vec![
+            (String::from("xyz"), 0.3),
+            (String::from("abc"), 0.26),
+            (String::from("abd"), 0.24),
+            (String::from("abe"), 0.2),
+        ];
+
+        assert_eq!(mbr_rerank(&results), Some((String::from("abc"), 0.26)));
+    }
+
+    #[test]
+    fn test_mbr_rerank_empty() {
+        assert_eq!(mbr_rerank(&[]), None);
+    }
+}
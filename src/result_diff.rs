@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::beam_entry::ProbabilityT;
+
+/// Change of a labeling present in both compared n-best lists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryChange {
+    pub labeling: String,
+    pub rank_a: usize,
+    pub rank_b: usize,
+    pub score_delta: ProbabilityT,
+}
+
+/// Structured difference between two n-best lists.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResultDiff {
+    /// Labelings present in both lists whose rank or score changed.
+    pub changed: Vec<EntryChange>,
+    /// Labelings present only in the first list.
+    pub only_in_a: Vec<String>,
+    /// Labelings present only in the second list.
+    pub only_in_b: Vec<String>,
+}
+
+impl ResultDiff {
+    /// Returns `true` if both lists contained the same labelings in the same order with equal scores.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.only_in_a.is_empty() && self.only_in_b.is_empty()
+    }
+}
+
+/// Compares two n-best lists as returned by `BeamState::sort` or `BeamState::sort_top_n`.
+///
+/// Score deltas are reported as `b - a`. Entries keep the order of the first list,
+/// followed by the order of the second list for labelings only present there.
+pub fn diff_results(a: &[(String, ProbabilityT)], b: &[(String, ProbabilityT)]) -> ResultDiff {
+    let ranks_b: HashMap<&str, (usize, ProbabilityT)> = b
+        .iter()
+        .enumerate()
+        .map(|(rank, (labeling, score))| (labeling.as_str(), (rank, *score)))
+        .collect();
+
+    let mut diff = ResultDiff::default();
+
+    for (rank_a, (labeling, score_a)) in a.iter().enumerate() {
+        match ranks_b.get(labeling.as_str()) {
+            Some(&(rank_b, score_b)) => {
+                if rank_a != rank_b || *score_a != score_b {
+                    diff.changed.push(EntryChange {
+                        labeling: labeling.clone(),
+                        rank_a,
+                        rank_b,
+                        score_delta: score_b - score_a,
+                    });
+                }
+            }
+            None => diff.only_in_a.push(labeling.clone()),
+        }
+    }
+
+    diff.only_in_b = b
+        .iter()
+        .filter(|(labeling, _)| !a.iter().any(|(other, _)| other == labeling))
+        .map(|(labeling, _)| labeling.clone())
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_results_identical() {
+        let a = vec![(String::from("a"), 0.5), (String::from("b"), 0.3)];
+
+        assert!(diff_results(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_diff_results_swapped_top_2() {
+        let a = vec![
+            (String::from("a"), 0.5),
+            (String::from("b"), 0.3),
+            (String::from("c"), 0.1),
+        ];
+        let b = vec![
+            (String::from("b"), 0.5),
+            (String::from("a"), 0.3),
+            (String::from("d"), 0.1),
+        ];
+
+        let diff = diff_results(&a, &b);
+
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].labeling, "a");
+        assert_eq!((diff.changed[0].rank_a, diff.changed[0].rank_b), (0, 1));
+        assert_eq!(diff.changed[0].score_delta, 0.3 - 0.5);
+        assert_eq!(diff.changed[1].labeling, "b");
+        assert_eq!((diff.changed[1].rank_a, diff.changed[1].rank_b), (1, 0));
+        assert_eq!(diff.only_in_a, vec![String::from("c")]);
+        assert_eq!(diff.only_in_b, vec![String::from("d")]);
+    }
+}
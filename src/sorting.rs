@@ -17,51 +17,136 @@ impl<T> ScoredValue<T> {
 
 impl<T> PartialOrd for ScoredValue<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.score.partial_cmp(&other.score)
+        Some(self.cmp(other))
     }
 }
 
+/// Orders by score using `f32::total_cmp`, which is a total order:
+/// `-NaN < -inf < ... < -0.0 < +0.0 < ... < +inf < +NaN`.
 impl<T> Ord for ScoredValue<T> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).expect("Scores should be comparable")
+        self.score.total_cmp(&other.score)
     }
 }
 
 impl<T> PartialEq for ScoredValue<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.score == other.score
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl<T> Eq for ScoredValue<T> {}
 
-/// Returns the top `n` elements with the highest scores from the given vector.
-pub fn top_n_elements<T>(v: Vec<ScoredValue<T>>, n: usize) -> Vec<ScoredValue<T>> {
-    if v.len() == 0 || n == 0 {
-        return Vec::new();
+/// Reusable heap and output storage for repeated top `n` selections.
+///
+/// Each call clears the buffers instead of reallocating them, which avoids
+/// allocation churn when selecting the top elements once per frame.
+#[derive(Debug)]
+pub struct TopNBuffer<T> {
+    heap: BinaryHeap<Reverse<ScoredValue<T>>>,
+    output: Vec<ScoredValue<T>>,
+}
+
+impl<T> TopNBuffer<T> {
+    /// Creates a new, empty `TopNBuffer`.
+    pub fn new() -> TopNBuffer<T> {
+        TopNBuffer::with_capacity(0)
+    }
+
+    /// Creates a new `TopNBuffer` with room for `n` elements.
+    pub fn with_capacity(n: usize) -> TopNBuffer<T> {
+        TopNBuffer {
+            heap: BinaryHeap::with_capacity(n),
+            output: Vec::with_capacity(n),
+        }
     }
 
-    let mut min_heap = BinaryHeap::with_capacity(n);
+    /// Selects the top `n` elements with the highest scores from `v`, reusing the buffers.
+    ///
+    /// Returns the selected elements sorted by descending score.
+    pub fn top_n_into(&mut self, v: Vec<ScoredValue<T>>, n: usize) -> &[ScoredValue<T>] {
+        self.heap.clear();
+        self.output.clear();
 
-    for scored_value in v {
-        if min_heap.len() < n {
-            min_heap.push(Reverse(scored_value));
-        } else if let Some(Reverse(min_entry)) = min_heap.peek() {
-            if scored_value.score > min_entry.score {
-                min_heap.pop();
-                min_heap.push(Reverse(scored_value));
+        if v.is_empty() || n == 0 {
+            return &self.output;
+        }
+
+        for scored_value in v {
+            if self.heap.len() < n {
+                self.heap.push(Reverse(scored_value));
+            } else if let Some(Reverse(min_entry)) = self.heap.peek() {
+                if scored_value > *min_entry {
+                    self.heap.pop();
+                    self.heap.push(Reverse(scored_value));
+                }
             }
         }
+
+        self.output
+            .extend(self.heap.drain().map(|Reverse(scored_value)| scored_value));
+
+        self.output.sort_by(|a, b| b.cmp(a));
+
+        &self.output
     }
+}
 
-    let mut entries: Vec<ScoredValue<T>> = min_heap
-        .into_iter()
-        .map(|Reverse(scored_value)| scored_value)
-        .collect();
+impl<T> Default for TopNBuffer<T> {
+    fn default() -> Self {
+        TopNBuffer::new()
+    }
+}
+
+/// Order in which `top_n_by_order` ranks scores.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Highest scores first.
+    #[default]
+    Descending,
+    /// Lowest scores first.
+    Ascending,
+}
+
+/// Returns the top `n` elements from the given vector, ranked by `order`.
+pub fn top_n_by_order<T>(
+    v: Vec<ScoredValue<T>>,
+    n: usize,
+    order: SortOrder,
+) -> Vec<ScoredValue<T>> {
+    match order {
+        SortOrder::Descending => top_n_elements(v, n),
+        SortOrder::Ascending => top_n_elements_by(v, n, |a, b| a.cmp(b)),
+    }
+}
+
+/// Returns the top `n` elements with the highest scores from the given vector.
+pub fn top_n_elements<T>(v: Vec<ScoredValue<T>>, n: usize) -> Vec<ScoredValue<T>> {
+    let mut buffer = TopNBuffer::with_capacity(n);
+    buffer.top_n_into(v, n);
+
+    buffer.output
+}
+
+/// Returns the top `n` elements according to `compare`, which orders better elements first.
+///
+/// The result is sorted by `compare`, so it is always a prefix of the fully sorted vector.
+pub fn top_n_elements_by<T, F>(mut v: Vec<T>, n: usize, mut compare: F) -> Vec<T>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if n == 0 {
+        return Vec::new();
+    }
+
+    if v.len() > n {
+        v.select_nth_unstable_by(n - 1, &mut compare);
+        v.truncate(n);
+    }
 
-    entries.sort_by(|a, b| b.score.partial_cmp(&a.score).expect("Scores should be comparable"));
+    v.sort_by(compare);
 
-    entries
+    v
 }
 
 #[cfg(test)]
@@ -95,4 +180,90 @@ mod tests {
         assert_eq!("a", sorted[0].value);
         assert_eq!("c", sorted[1].value);
     }
+
+    #[test]
+    fn test_top_n_elements_total_order() {
+        let values = vec![
+            ScoredValue::new("neg_zero", -0.0),
+            ScoredValue::new("nan", f32::NAN),
+            ScoredValue::new("zero", 0.0),
+            ScoredValue::new("one", 1.0),
+        ];
+        let sorted = top_n_elements(values, 4);
+
+        let order: Vec<&str> = sorted
+            .iter()
+            .map(|scored_value| scored_value.value)
+            .collect();
+        assert_eq!(order, vec!["nan", "one", "zero", "neg_zero"]);
+
+        assert_eq!(
+            ScoredValue::new((), f32::NAN),
+            ScoredValue::new((), f32::NAN)
+        );
+        assert_ne!(ScoredValue::new((), 0.0), ScoredValue::new((), -0.0));
+    }
+
+    #[test]
+    fn test_top_n_by_order() {
+        let values = vec![
+            ScoredValue::new("c", 0.7),
+            ScoredValue::new("b", 0.5),
+            ScoredValue::new("a", 1.0),
+            ScoredValue::new("d", 0.2),
+        ];
+
+        let smallest = top_n_by_order(values.clone(), 2, SortOrder::Ascending);
+        assert_eq!(smallest.len(), 2);
+        assert_eq!("d", smallest[0].value);
+        assert_eq!("b", smallest[1].value);
+
+        let largest = top_n_by_order(values, 2, SortOrder::default());
+        assert_eq!("a", largest[0].value);
+        assert_eq!("c", largest[1].value);
+    }
+
+    #[test]
+    fn test_top_n_elements_by() {
+        let values = vec![("c", 0.5), ("b", 0.5), ("a", 1.0), ("d", 0.1)];
+        let sorted = top_n_elements_by(values, 3, |a, b| {
+            b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(b.0))
+        });
+
+        assert_eq!(sorted, vec![("a", 1.0), ("b", 0.5), ("c", 0.5)]);
+    }
+
+    #[test]
+    fn test_top_n_buffer_reuse() {
+        let inputs = vec![
+            vec![
+                ScoredValue::new("c", 0.7),
+                ScoredValue::new("b", 0.5),
+                ScoredValue::new("a", 1.0),
+            ],
+            vec![ScoredValue::new("d", 0.2), ScoredValue::new("e", 0.9)],
+            vec![],
+            vec![
+                ScoredValue::new("f", 0.1),
+                ScoredValue::new("g", 0.3),
+                ScoredValue::new("h", 0.2),
+            ],
+        ];
+
+        let mut buffer = TopNBuffer::new();
+
+        for input in inputs {
+            let mut expected = input.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+            expected.truncate(2);
+
+            let result = buffer.top_n_into(input, 2);
+
+            assert_eq!(result.len(), expected.len());
+            for (a, b) in result.iter().zip(expected.iter()) {
+                assert_eq!(a.value, b.value);
+                assert_eq!(a.score, b.score);
+            }
+        }
+    }
 }
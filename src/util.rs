@@ -0,0 +1,56 @@
+use crate::beam_entry::ProbabilityT;
+
+/// Default tolerance used when comparing accumulated probabilities.
+pub const PROBABILITY_EPSILON: ProbabilityT = 1e-6;
+
+/// Returns `true` if `a` and `b` differ by at most `eps`.
+pub fn approx_eq(a: ProbabilityT, b: ProbabilityT, eps: ProbabilityT) -> bool {
+    (a - b).abs() <= eps
+}
+
+/// Returns the Levenshtein distance between `a` and `b`, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, char_a) in a.chars().enumerate() {
+        current[0] = i + 1;
+
+        for (j, char_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(char_a != *char_b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq_within_eps() {
+        assert!(approx_eq(0.1 + 0.2, 0.3, PROBABILITY_EPSILON));
+        assert!(approx_eq(1.0, 1.05, 0.1));
+    }
+
+    #[test]
+    fn test_approx_eq_outside_eps() {
+        assert!(!approx_eq(0.3, 0.31, PROBABILITY_EPSILON));
+        assert!(!approx_eq(1.0, 1.2, 0.1));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("hello", "hello"), 0);
+        assert_eq!(edit_distance("über", "uber"), 1);
+    }
+}